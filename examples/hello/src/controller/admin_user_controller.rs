@@ -1,11 +1,42 @@
-use tiny::Context;
+use tiny::{multipart::MultipartConfig, Context, Extension, Json, State, StatusCode};
 
-use crate::service::user_service;
+use crate::{
+    middleware::auth::CurrentUser,
+    model::user::User,
+    service::{error::ServiceError, user_service::UserService},
+};
 
+//当前用户由Auth中间件写入
+pub async fn get_user(
+    service: State<UserService>,
+    Extension(current): Extension<CurrentUser>,
+) -> Result<Json<User>, ServiceError> {
+    let user = service.get_user_info(&current.user_id).await?;
+    Ok(Json(user))
+}
 
-
-pub fn get_user(c: &mut Context) {
-    let user_id = "12345678"; //可以从Context上下文中获取
-    let user = user_service::UserService::get_user_info(user_id).unwrap();
-    c.json(user);
-}
\ No newline at end of file
+//上传头像：multipart/form-data，大于1M的文件写入临时目录
+pub async fn upload_avatar(c: &mut Context) {
+    let config = MultipartConfig::new().max_file_size(2 << 20).spool(1 << 20);
+    let mut multipart = match c.multipart_with(config) {
+        Ok(multipart) => multipart,
+        Err(e) => return c.string(Some(StatusCode::BAD_REQUEST), &e.to_string()),
+    };
+    let mut files = Vec::new();
+    loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.is_file() => match field.file().await {
+                Ok(file) => files.push(format!(
+                    "{}:{}",
+                    file.file_name.unwrap_or_default(),
+                    file.size
+                )),
+                Err(e) => return c.string(Some(StatusCode::BAD_REQUEST), &e.to_string()),
+            },
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(e) => return c.string(Some(StatusCode::BAD_REQUEST), &e.to_string()),
+        }
+    }
+    c.json(files);
+}
@@ -1,5 +1,9 @@
 use tiny::Context;
 
-pub fn index(c: &mut Context) {
+pub async fn index(c: &mut Context) {
     c.string(None, "hello world")
-}
\ No newline at end of file
+}
+//host中的参数：acme.localhost => acme
+pub async fn whoami(c: &mut Context) -> String {
+    c.host_param::<String>("tenant").unwrap_or_default()
+}
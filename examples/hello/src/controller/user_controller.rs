@@ -1,16 +1,62 @@
-use tiny::Context;
+use serde::{Deserialize, Serialize};
+use tiny::{
+    extract::{Path, Query},
+    Context, Error, Json, State, StatusCode,
+};
 
-use crate::service::user_service;
+use crate::{
+    model::user::User,
+    service::{error::ServiceError, user_service::UserService},
+};
 
-pub fn get_user(c: &mut Context) {
+pub async fn get_user(service: State<UserService>) -> Result<Json<User>, ServiceError> {
     let user_id = "12345678"; //可以从Context上下文中获取
-    let user = user_service::UserService::get_user_info(user_id).unwrap();
-    c.json(user);
+    let user = service.get_user_info(user_id).await?;
+    Ok(Json(user))
 }
 
-pub fn get_user2(c: &mut Context) {
+pub async fn get_user2(c: &mut Context) {
     let user_id = "12345678"; //可以从Context上下文中获取
-    let user = user_service::UserService::get_user_info(user_id).unwrap();
+    let service = c.state::<UserService>().unwrap();
+    let user = service.get_user_info(user_id).await.unwrap();
     c.json(user);
     panic!()
 }
+
+//请求体解析失败时通过`?`转换为tiny::Error，自动返回400，校验失败返回422
+pub async fn add_user(c: &mut Context) -> Result<(StatusCode, Json<User>), Error> {
+    //从请求体中解析json并校验
+    let user = c.bind_valid_json::<User>().await?;
+    //模拟新增用户的id，Location指向用户详情
+    let location = c.url_for("user.detail", &[("id", "1")])?;
+    c.set_header("location", &location);
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Paging {
+    page: u32,
+    size: Option<u32>,
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+//查询参数绑定：/user/list?page=1&size=10&tag=a&tag=b，参数错误时自动返回400
+pub async fn list_users(Query(paging): Query<Paging>) -> Json<Paging> {
+    Json(paging)
+}
+
+//路径参数：/user/detail/7
+pub async fn get_user_by_id(
+    service: State<UserService>,
+    Path(id): Path<u64>,
+) -> Result<Json<User>, ServiceError> {
+    let user = service.get_user_info(&id.to_string()).await?;
+    Ok(Json(user))
+}
+
+//分组内的404：/user下未匹配的路径返回json
+pub async fn not_found(c: &mut Context) -> (StatusCode, Json<serde_json::Value>) {
+    let body = serde_json::json!({ "msg": "接口不存在", "path": c.path });
+    (StatusCode::NOT_FOUND, Json(body))
+}
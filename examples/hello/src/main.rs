@@ -20,6 +20,8 @@ async fn main() {
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     //default()此方式会添加错误处理中间件，new()则不添加
     let mut r = default();
+    //注册应用级共享状态，handler中通过State<T>或c.state::<T>()获取
+    r = r.with_state(service::user_service::UserService::new(18));
     //添加日志中间件;
     r = r.hooks(middleware::logger::Logger::logger);
     r = router::route::route(r);
@@ -35,14 +37,36 @@ async fn main() {
 //输入：http://127.0.0.1:3000/user/info
 //输出：{"name":"hmm","age":18}
 //经过中间件：recovery(错误处理),logger（日志中间件）
+//b2)测试405：路径存在但请求方法不匹配
+//输入：curl -X POST http://127.0.0.1:3000/user/info
+//输出：405 method not allowed，响应头Allow: GET, HEAD, OPTIONS
+//HEAD/OPTIONS自动处理：curl -I http://127.0.0.1:3000/index，curl -X OPTIONS -i http://127.0.0.1:3000/index
+//b3)路径规范化：多余的'/'、'..'以及末尾'/'会重定向到规范路径（Engine::path_policy可改为严格匹配）
+//输入：curl -i http://127.0.0.1:3000/user/info/
+//输出：301 Location: /user/info
+//b4)404：未匹配的路径返回404（同样经过日志中间件），/user下返回json
+//输入：curl -i http://127.0.0.1:3000/user/none
+//输出：404 {"msg":"接口不存在","path":"/user/none"}
+//b5)按Host路由：子域名作为参数
+//输入：curl -H 'Host: acme.localhost' http://127.0.0.1:3000/whoami
+//输出：acme
 //c)测试容错能力：panic情况
 //输入：http://127.0.0.1:3000/user/info2
-//输出：系统开小差
+//输出：500 internal server error
+//经过中间件：recovery(错误处理),logger（日志中间件）
+
+//c2)测试请求体解析能力
+//输入：curl -X POST http://127.0.0.1:3000/user/add -d '{"name":"hmm","age":18}'
+//输出：201 {"name":"hmm","age":18}，响应头Location: /user/detail/1（由命名路由生成）
+//校验失败：curl -X POST http://127.0.0.1:3000/user/add -d '{"name":"","age":0}'
+//输出：422 {"errors":[{"field":"name","message":"length must be between 1 and 32"},...]}
 //经过中间件：recovery(错误处理),logger（日志中间件）
 
 //d)测试中间件能力,这里主要模拟鉴权中间件
 //输入：http://127.0.0.1:3000/admin/userinfo
-//输出：权限不足
+//输出：401 {"msg":"权限不足"}
 //经过中间件：recovery(错误处理),logger（日志中间件）,权限中间件
+//携带token：curl -H 'token: 123456' http://127.0.0.1:3000/admin/userinfo
+//输出：{"name":"hmm","age":18}，用户由权限中间件写入Context扩展后传给handler
 
 
@@ -1,23 +1,34 @@
-use tiny::Context;
+use serde_json::json;
+use tiny::{Context, StatusCode};
 
 //权限中间件demo
 pub struct Auth;
 
+//鉴权通过的当前用户：由Auth写入请求扩展，handler中使用Extension<CurrentUser>获取
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub user_id: String,
+}
+
 
 impl Auth {
-    pub fn auth(c: &mut Context) {
+    pub async fn auth(c: &mut Context) {
         //模拟,用户可以从Context获取真实token
         let token = c
             .header::<String>("token")
-            .or_else(|e| Ok::<String, ()>("12345".to_string()))
+            .or_else(|_e| Ok::<String, ()>("12345".to_string()))
             .unwrap();
 
         if "123456" == token.as_str() {
+            //模拟根据token查出用户
+            c.extensions_mut().insert(CurrentUser {
+                user_id: "12345678".to_string(),
+            });
             //进行后面逻辑
-            c.next();
+            c.next().await;
         } else {
-            c.done();
-            c.string(None, "权限不足");
+            //结束调用链并返回401
+            c.abort_with_json(StatusCode::UNAUTHORIZED, json!({ "msg": "权限不足" }));
         }
     }
 }
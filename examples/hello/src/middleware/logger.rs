@@ -1,16 +1,20 @@
 use log::info;
-use std::time::{self, Instant};
+use std::time;
 use tiny::Context;
 
 //日志中间件demo
 pub struct Logger;
 
 impl Logger {
-    pub fn logger(c: &mut Context) {
+    pub async fn logger(c: &mut Context) {
         let begin = time::Instant::now();
         info!("the path is:{}", &c.path);
-        c.next();
+        c.next().await;
         let cost = time::Instant::elapsed(&begin).as_millis();
-        info!("cost time:{}", cost);
+        if c.is_aborted() {
+            info!("aborted, status:{}, cost time:{}", c.response.status(), cost);
+        } else {
+            info!("status:{}, cost time:{}", c.response.status(), cost);
+        }
     }
 }
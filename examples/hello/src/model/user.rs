@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use tiny::{Validate, ValidationErrors};
 
 
 
@@ -13,3 +14,13 @@ impl User {
         Self { name, age }
     }
 }
+
+//绑定请求体后执行的校验，失败时返回422并列出字段错误
+impl Validate for User {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut v = ValidationErrors::new();
+        v.length("name", &self.name, 1, 32);
+        v.range("age", self.age, 1, 150);
+        v.into_result()
+    }
+}
@@ -2,8 +2,11 @@ use tiny::Engine;
 
 use crate::controller;
 
-pub(crate) fn route(r: Engine) -> Engine {
+pub(crate) fn router() -> Engine {
     //简单路由功能
-    let r = r.get("/index", controller::index_controller::index);
+    let mut r = tiny::new().get("/index", controller::index_controller::index);
+    //按Host路由：子域名作为参数，如：acme.localhost
+    r.host(":tenant.localhost")
+        .get("/whoami", controller::index_controller::whoami);
     r
 }
@@ -1,10 +1,10 @@
 use tiny::Engine;
 
 pub(crate) fn route(r: Engine) -> Engine {
+    //各模块独立构建路由（各自的中间件），在此组合；路由冲突时启动即panic
     //index不需要鉴权与登录
-    let mut r = super::index_router::route(r);
-    r = super::user_router::route(r);
-    //需要鉴权通过
-    r = super::admin_user_router::route(r);
-    r
+    r.merge(super::index_router::router())
+        .nest("/user", super::user_router::router())
+        //需要鉴权通过
+        .nest("/admin", super::admin_user_router::router())
 }
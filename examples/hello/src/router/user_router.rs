@@ -2,12 +2,20 @@ use tiny::Engine;
 
 use crate::controller;
 
-pub(crate) fn route(mut r: Engine) -> Engine {
-    //路由分组
-    let _user_group = r
-        .group("/user")
+pub(crate) fn router() -> Engine {
+    //挂载到/user下
+    tiny::new()
         .get("/info", controller::user_controller::get_user)
+        //未匹配的路径：json格式的404
+        .no_route(controller::user_controller::not_found)
+        //查询参数绑定：GET、POST共用同一handler
+        .match_methods(&["GET", "POST"], "/list", controller::user_controller::list_users)
+        //提取器：handler参数直接从请求中提取；:id<u64>约束id为数字，否则不匹配该路由
+        .get("/detail/:id<u64>", controller::user_controller::get_user_by_id)
+        //命名路由：通过c.url_for("user.detail", ..)生成url
+        .name("user.detail")
         //模拟panic
-        .get("/info2", controller::user_controller::get_user2);
-    r
+        .get("/info2", controller::user_controller::get_user2)
+        //解析json请求体
+        .post("/add", controller::user_controller::add_user)
 }
@@ -0,0 +1,15 @@
+use tiny::{Body, IntoResponse, Response, StatusCode};
+
+//业务错误：实现IntoResponse后，controller中可以直接使用`?`
+#[derive(Debug)]
+pub enum ServiceError {
+    UserNotFound,
+}
+
+impl IntoResponse for ServiceError {
+    fn into_response(self) -> Response<Body> {
+        match self {
+            ServiceError::UserNotFound => (StatusCode::NOT_FOUND, "用户不存在").into_response(),
+        }
+    }
+}
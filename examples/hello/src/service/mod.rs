@@ -1,2 +1,3 @@
 
 pub mod user_service;
+pub mod error;
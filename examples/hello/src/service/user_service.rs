@@ -1,14 +1,27 @@
-use std::convert::Infallible;
+use std::time::Duration;
 
 use crate::model::{self, user::User};
 
-pub struct UserService;
+use super::error::ServiceError;
+
+//通过Engine::with_state注册为共享状态，controller中使用State<UserService>获取
+pub struct UserService {
+    default_age: u8,
+}
 
 
 impl  UserService{
-    pub fn get_user_info(user_id:&str)->Result<User,Infallible>{
-        //模拟从数据库中取出
-        let user = model::user::User::new("hmm".to_string(), 18);
+    pub fn new(default_age: u8) -> Self {
+        UserService { default_age }
+    }
+
+    pub async fn get_user_info(&self, user_id:&str)->Result<User,ServiceError>{
+        //模拟从数据库中取出（异步io）
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        if user_id.is_empty() {
+            return Err(ServiceError::UserNotFound);
+        }
+        let user = model::user::User::new("hmm".to_string(), self.default_age);
         Ok(user)
     }
 }
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hyper::{Body, Request};
+use tiny::{Context, Engine};
+
+//路由表：每组4条路由（静态、参数、带约束的参数、通配符），共3000条
+const GROUPS: usize = 750;
+
+fn engine() -> Engine {
+    let mut engin = tiny::new();
+    for i in 0..GROUPS {
+        engin = engin
+            .get(
+                format!("/api/v1/resource{}", i),
+                async |_c: &mut Context| {},
+            )
+            .get(
+                format!("/api/v1/resource{}/:id", i),
+                async |_c: &mut Context| {},
+            )
+            .post(
+                format!("/api/v1/resource{}/:id/items/:item<u64>", i),
+                async |_c: &mut Context| {},
+            )
+            .get(format!("/static{}/*file", i), async |_c: &mut Context| {});
+    }
+    engin
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let engin = Arc::new(engine());
+    let requests = [
+        ("static", "GET", "/api/v1/resource749"),
+        ("param", "GET", "/api/v1/resource375/42"),
+        ("constraint", "POST", "/api/v1/resource1/42/items/7"),
+        ("catchall", "GET", "/static500/css/app.css"),
+        ("not_found", "GET", "/api/v2/none"),
+    ];
+    let mut group = c.benchmark_group("router");
+    for (name, method, path) in requests {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let req = Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .body(Body::empty())
+                    .unwrap();
+                let resp = runtime.block_on(Engine::handler(req, engin.clone()));
+                black_box(resp)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
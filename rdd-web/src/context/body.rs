@@ -0,0 +1,83 @@
+use std::fmt::Display;
+
+use hyper::{
+    body::{Bytes, HttpBody},
+    header, Body, Request,
+};
+use serde::de::DeserializeOwned;
+
+use crate::BoxErr;
+
+//读取完整请求体，读取后原body被置空，因此只能读取一次（Context中会缓存读取结果）
+//超过limit字节时返回PayloadTooLarge：Content-Length超过限制时不读取，否则读取过程中累计检查
+pub(crate) async fn read_body(
+    req: &mut Request<Body>,
+    limit: u64,
+) -> Result<Bytes, ExtractBodyError> {
+    let too_large = ExtractBodyError::PayloadTooLarge { limit };
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if length.is_some_and(|length| length > limit) {
+        return Err(too_large);
+    }
+    let mut body = std::mem::take(req.body_mut());
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| ExtractBodyError::ReadBody { source: e.into() })?;
+        if (bytes.len() + chunk.len()) as u64 > limit {
+            return Err(too_large);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+pub(crate) fn string(bytes: &Bytes) -> Result<String, ExtractBodyError> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|e| ExtractBodyError::InvalidUtf8 { source: e.into() })
+}
+
+pub(crate) fn json<T>(bytes: &Bytes) -> Result<T, ExtractBodyError>
+where
+    T: DeserializeOwned,
+{
+    if bytes.is_empty() {
+        return Err(ExtractBodyError::MissingBody);
+    }
+    serde_json::from_slice(bytes).map_err(|e| ExtractBodyError::InvalidJson { source: e.into() })
+}
+
+#[derive(Debug)]
+pub enum ExtractBodyError {
+    ReadBody { source: BoxErr },
+    //请求体超过Engine::max_body_size
+    PayloadTooLarge { limit: u64 },
+    MissingBody,
+    InvalidUtf8 { source: BoxErr },
+    InvalidJson { source: BoxErr },
+}
+
+impl Display for ExtractBodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractBodyError::ReadBody { source } => {
+                write!(f, "failed to read request body({})", source)
+            }
+            ExtractBodyError::PayloadTooLarge { limit } => {
+                write!(f, "request body exceeds size limit:{}", limit)
+            }
+            ExtractBodyError::MissingBody => write!(f, "missing request body"),
+            ExtractBodyError::InvalidUtf8 { source: _ } => {
+                write!(f, "request body is not valid utf-8")
+            }
+            ExtractBodyError::InvalidJson { source } => {
+                write!(f, "failed to deserialize json body({})", source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractBodyError {}
@@ -1,85 +1,366 @@
-use std::{collections::HashMap, fmt::Debug, str::FromStr};
+use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::Arc};
 
 use hyper::{
+    body::Bytes,
     header::{self, HeaderName, HeaderValue},
+    http::Extensions,
     Body, Request, Response, StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{router::handler::Handler, BoxErr};
+use crate::{
+    error::ErrorRenderer, router::trie::Chain, BoxErr, Engine, Error, IntoResponse, UrlForError,
+    Validate,
+};
 
-use super::{header::ExtractHeaderError, param::ExtractParamError, query::ExtractQueryError};
+use super::{
+    body::ExtractBodyError,
+    form::ExtractFormError,
+    header::ExtractHeaderError,
+    multipart::{ExtractMultipartError, Multipart, MultipartConfig},
+    param::ExtractParamError,
+    query::ExtractQueryError,
+    state::ExtractStateError,
+    Keys,
+};
 //上下文：为每一个请示创建上下文环境：主要包括req内容(已经解析出来),response，以及与此请求相关的
 //handler列表
-pub struct Context<'h, 'req> {
+pub struct Context {
+    //路径参数（已解码）
     pub params: HashMap<String, String>,
-    pub forms: HashMap<String, String>,
-    pub request: &'req Request<Body>,
+    //路径参数原始值（未解码）
+    pub(crate) raw_params: HashMap<String, String>,
+    //按路由中出现顺序排列的路径参数
+    pub(crate) path_params: Vec<(String, String)>,
+    //按Host路由时host中的参数：:tenant.example.com => tenant
+    pub(crate) host_params: HashMap<String, String>,
+    //application/x-www-form-urlencoded表单，重复的key只保留第一个值；首次读取表单时解析
+    forms: Option<HashMap<String, String>>,
+    //表单全部键值对（包括重复的key）
+    form_pairs: Vec<(String, String)>,
+    pub request: Request<Body>,
+    //已读取的请求体缓存：body只能从request中读取一次
+    body: Option<Bytes>,
     pub path: String,
     pub method: String,
     //handlers：路由匹配的handler，以及该路由对应的所有中间件
     //组成handlers列表（些列表已排序）：1）执全局中间件（如果有） 2）分组中间件（如果有）3）节点中间件（如果有）4）路由handler
-    pub(crate) handlers: Vec<&'h Handler>,
+    //匹配到路由时直接使用叶子节点上已解析的调用链
+    pub(crate) handlers: Chain,
     index: i32,
+    //调用链是否被提前结束
+    aborted: bool,
     pub response: Response<Body>,
+    //请求所在的web引擎：读取应用级共享状态等
+    engin: Arc<Engine>,
+    //请求级键值存储：c.set/c.get
+    keys: Keys,
+    //请求级扩展：按类型保存，每种类型只保存一份
+    extensions: Extensions,
 }
-impl<'h, 'req> Context<'h, 'req> {
-    pub(crate) fn new(request: &'req Request<Body>) -> Self {
+impl Context {
+    pub(crate) fn new(request: Request<Body>, engin: Arc<Engine>) -> Self {
         Self {
             params: HashMap::new(),
-            forms: HashMap::new(),
-            handlers: Vec::new(),
+            raw_params: HashMap::new(),
+            path_params: Vec::new(),
+            host_params: HashMap::new(),
+            forms: None,
+            form_pairs: Vec::new(),
+            handlers: Arc::new([]),
             request,
+            body: None,
             path: "".to_string(),
             method: "".to_string(),
             index: -1,
+            aborted: false,
             response: Response::new(Body::default()),
+            engin,
+            keys: Keys::new(),
+            extensions: Extensions::new(),
         }
     }
 }
 
-impl<'h, 'req> Context<'h, 'req> {
+impl Context {
     //请求对应执行链条：1）执全局中间件（如果有） 2）分组中间件（如果有）3）节点中间件（如果有）4）路由handler
-    pub fn next(&mut self) {
+    //中间件中调用c.next().await即可等待下游handler执行完成
+    pub async fn next(&mut self) {
         self.index += 1;
         let len = self.handlers.len() as i32;
         while self.index < len {
-            if let Some(&handler) = &self.handlers.get(self.index as usize){
-                handler(self);
+            if let Some(handler) = self.handlers.get(self.index as usize).cloned() {
+                handler(self).await;
             }
             //序号+1
             self.index += 1;
         }
-        
     }
     //完成，主要要中间件中使用，一般在由于特别情况需要提前结束调用链。
     //例如：权限中间件在验证用户权限不足时，不需要再调用下游组件，提前结束调用链。
-    pub fn done(&mut self){
+    pub fn done(&mut self) {
         self.index = self.handlers.len() as i32;
+        self.aborted = true;
+    }
+
+    //提前结束调用链并设置响应状态码，如：权限不足返回401
+    pub fn abort_with_status(&mut self, code: StatusCode) {
+        self.done();
+        *self.response.status_mut() = code;
+    }
+
+    //提前结束调用链并返回json
+    pub fn abort_with_json<T>(&mut self, code: StatusCode, json: T)
+    where
+        T: Serialize,
+    {
+        self.json(json);
+        self.abort_with_status(code);
+    }
+
+    //调用链是否已被提前结束：上游中间件在c.next().await之后可据此判断
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
     }
+
     pub fn header<T>(&self, name: &str) -> Result<T, ExtractHeaderError>
     where
         T: FromStr,
         T::Err: Into<BoxErr>,
     {
-        super::header::header(self.request, name)
+        super::header::header(&self.request, name)
+    }
+
+    //查询参数绑定：?page=2&size=10&tag=a&tag=b&filter[status]=x
+    pub fn bind_query<T>(&self) -> Result<T, ExtractQueryError>
+    where
+        T: DeserializeOwned,
+    {
+        super::query::query(&self.request)
+    }
+
+    pub fn query_value<T>(&self, name: &str) -> Result<T, ExtractQueryError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxErr>,
+    {
+        super::query::query_value(&self.request, name)
     }
 
-    pub(crate) fn query<'de, T>(req: &'de Request<Body>) -> Result<T, ExtractQueryError>
+    //重复的查询key：tag=a&tag=b
+    pub fn query_array<T>(&self, name: &str) -> Result<Vec<T>, ExtractQueryError>
     where
-        T: Deserialize<'de>,
+        T: FromStr,
+        T::Err: Into<BoxErr>,
     {
-        super::query::query(req)
+        super::query::query_array(&self.request, name)
     }
 
+    //路径参数；路径中没有该参数时回退到host参数，同名时路径参数优先
     pub fn param<T>(&self, name: &str) -> Result<T, ExtractParamError>
     where
         T: FromStr,
         T::Err: Into<BoxErr>,
     {
+        if !self.params.contains_key(name) && self.host_params.contains_key(name) {
+            return self.host_param(name);
+        }
         super::param::param(&self.params, name)
     }
 
+    //host中的参数：Engine::host(":tenant.example.com")，请求acme.example.com时tenant为acme
+    //与路径参数分开保存，同名时互不覆盖；只读取host参数时使用
+    pub fn host_param<T>(&self, name: &str) -> Result<T, ExtractParamError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxErr>,
+    {
+        super::param::param(&self.host_params, name)
+    }
+
+    //未解码的路径参数：/hello/%E4%BD%A0 => %E4%BD%A0
+    pub fn raw_param(&self, name: &str) -> Option<&str> {
+        self.raw_params.get(name).map(String::as_str)
+    }
+
+    //根据路由名称生成url：c.url_for("user.show", &[("id", "7")]) => /user/7
+    //未在路由中使用的参数作为查询参数
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlForError> {
+        self.engin.router.url_for(name, params)
+    }
+
+    //读取通过Engine::with_state注册的应用级共享状态
+    pub fn state<T>(&self) -> Result<Arc<T>, ExtractStateError>
+    where
+        T: Send + Sync + 'static,
+    {
+        super::state::state(&self.engin.states)
+    }
+
+    pub(crate) fn error_renderer(&self) -> Option<Arc<ErrorRenderer>> {
+        self.engin.error_renderer.clone()
+    }
+
+    //请求级键值存储：中间件中c.set("user_id", 7u64)，handler中c.get::<u64>("user_id")
+    pub fn set<T>(&mut self, key: &str, value: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.keys.insert(key.to_string(), Box::new(value));
+    }
+
+    //key不存在或类型不匹配时返回None
+    pub fn get<T>(&self, key: &str) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        super::extension::get(&self.keys, key)
+    }
+
+    //请求级扩展：c.extensions_mut().insert(user)，c.extensions().get::<User>()
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    //读取请求体原始字节，首次读取后缓存，后续中间件/handler可重复读取
+    pub async fn body_bytes(&mut self) -> Result<Bytes, ExtractBodyError> {
+        if let Some(bytes) = &self.body {
+            return Ok(bytes.clone());
+        }
+        let bytes = super::body::read_body(&mut self.request, self.engin.max_body_size).await?;
+        self.body = Some(bytes.clone());
+        Ok(bytes)
+    }
+
+    pub async fn body_string(&mut self) -> Result<String, ExtractBodyError> {
+        let bytes = self.body_bytes().await?;
+        super::body::string(&bytes)
+    }
+
+    pub async fn bind_json<T>(&mut self) -> Result<T, ExtractBodyError>
+    where
+        T: DeserializeOwned,
+    {
+        let bytes = self.body_bytes().await?;
+        super::body::json(&bytes)
+    }
+
+    //绑定json后执行校验：校验失败返回Error::Validation（422）
+    pub async fn bind_valid_json<T>(&mut self) -> Result<T, Error>
+    where
+        T: DeserializeOwned + Validate,
+    {
+        let value = self.bind_json::<T>().await?;
+        value.validate()?;
+        Ok(value)
+    }
+
+    pub fn bind_valid_query<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned + Validate,
+    {
+        let value = self.bind_query::<T>()?;
+        value.validate()?;
+        Ok(value)
+    }
+
+    pub async fn bind_valid_form<T>(&mut self) -> Result<T, Error>
+    where
+        T: DeserializeOwned + Validate,
+    {
+        let value = self.bind_form::<T>().await?;
+        value.validate()?;
+        Ok(value)
+    }
+
+    pub async fn form<T>(&mut self, name: &str) -> Result<T, ExtractFormError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxErr>,
+    {
+        self.parse_form().await?;
+        super::form::form(&self.form_pairs, name)
+    }
+
+    //重复的表单key：tag=a&tag=b
+    pub async fn form_array<T>(&mut self, name: &str) -> Result<Vec<T>, ExtractFormError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxErr>,
+    {
+        self.parse_form().await?;
+        super::form::form_array(&self.form_pairs, name)
+    }
+
+    //全部表单字段，重复的key只保留第一个值
+    pub async fn forms(&mut self) -> Result<&HashMap<String, String>, ExtractFormError> {
+        self.parse_form().await?;
+        Ok(self.forms.get_or_insert_with(HashMap::new))
+    }
+
+    //与form相同，Content-Type不是application/x-www-form-urlencoded时按空表单处理，不读取请求体
+    pub async fn bind_form<T>(&mut self) -> Result<T, ExtractFormError>
+    where
+        T: DeserializeOwned,
+    {
+        if !super::form::is_form(&self.request) {
+            return super::form::bind_form(&Bytes::new());
+        }
+        let bytes = self
+            .body_bytes()
+            .await
+            .map_err(ExtractFormError::InvalidBody)?;
+        super::form::bind_form(&bytes)
+    }
+
+    //解析multipart/form-data请求体（使用默认限制）
+    pub fn multipart(&mut self) -> Result<Multipart, ExtractMultipartError> {
+        self.multipart_with(MultipartConfig::default())
+    }
+
+    pub fn multipart_with(
+        &mut self,
+        config: MultipartConfig,
+    ) -> Result<Multipart, ExtractMultipartError> {
+        let body = match &self.body {
+            Some(bytes) => Body::from(bytes.clone()),
+            None => std::mem::take(self.request.body_mut()),
+        };
+        super::multipart::multipart(&self.request, body, config)
+    }
+
+    //首次读取表单时解析urlencoded请求体（受Engine::max_body_size限制），其他类型的请求体不读取
+    async fn parse_form(&mut self) -> Result<(), ExtractFormError> {
+        if self.forms.is_some() {
+            return Ok(());
+        }
+        if super::form::is_form(&self.request) {
+            let bytes = self
+                .body_bytes()
+                .await
+                .map_err(ExtractFormError::InvalidBody)?;
+            self.form_pairs = super::form::parse_pairs(&bytes)?;
+        }
+        let mut forms = HashMap::new();
+        for (k, v) in self.form_pairs.iter() {
+            forms.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        self.forms = Some(forms);
+        Ok(())
+    }
+
+    //路径参数绑定：/user/:id => u64，/user/:id/:name => (u64,String)或结构体
+    pub fn bind_path<T>(&self) -> Result<T, ExtractParamError>
+    where
+        T: DeserializeOwned,
+    {
+        super::param::path(&self.path_params)
+    }
+
     pub fn set_header(&mut self, name: &str, value: &str) {
         let headers = self.response.headers_mut();
         headers.insert(
@@ -96,8 +377,13 @@ impl<'h, 'req> Context<'h, 'req> {
             header::CONTENT_TYPE.as_str(),
             "application/json; charset=utf-8",
         );
-        let data = serde_json::to_string(&json).unwrap();
-        *self.response.body_mut() = Body::from(data.to_string());
+        match serde_json::to_string(&json) {
+            Ok(data) => *self.response.body_mut() = Body::from(data),
+            Err(e) => {
+                let resp = Error::Render { source: e.into() }.into_response();
+                crate::response::write_response(self, resp);
+            }
+        }
     }
 
     pub fn string(&mut self, code: Option<StatusCode>, data: &str) {
@@ -117,20 +403,23 @@ impl<'h, 'req> Context<'h, 'req> {
         *self.response.body_mut() = Body::from(data.to_string());
     }
 
-    pub(crate) fn build_request(req: &Request<Body>) -> Context<'_, '_> {
-        let mut context = Context::new(req);
-        context.method = req.method().as_str().to_string();
-        context.path = req.uri().path().to_string();
+    pub(crate) fn build_request(req: Request<Body>, engin: Arc<Engine>) -> Context {
+        let method = req.method().as_str().to_string();
+        let path = req.uri().path().to_string();
+        let mut context = Context::new(req, engin);
+        context.method = method;
+        context.path = path;
         context
     }
 }
 
-impl<'h, 'req> Debug for Context<'h, 'req> {
+impl Debug for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Context")
             .field("name", &self.method)
             .field("path", &self.path)
             .field("params", &self.params)
+            .field("aborted", &self.aborted)
             .finish()
     }
 }
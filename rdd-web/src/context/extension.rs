@@ -0,0 +1,57 @@
+use std::{
+    any::{type_name, Any},
+    collections::HashMap,
+    fmt::Display,
+};
+
+//请求级键值存储：中间件写入，下游handler读取（如当前用户、请求ID等）
+pub(crate) type Keys = HashMap<String, Box<dyn Any + Send + Sync>>;
+
+pub(crate) fn get<'k, T>(keys: &'k Keys, key: &str) -> Option<&'k T>
+where
+    T: Send + Sync + 'static,
+{
+    keys.get(key).and_then(|value| value.downcast_ref::<T>())
+}
+
+#[derive(Debug)]
+pub enum ExtractExtensionError {
+    MissingExtension { type_name: &'static str },
+}
+
+impl ExtractExtensionError {
+    pub(crate) fn missing<T>() -> Self {
+        ExtractExtensionError::MissingExtension {
+            type_name: type_name::<T>(),
+        }
+    }
+}
+
+impl Display for ExtractExtensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractExtensionError::MissingExtension { type_name } => write!(
+                f,
+                "missing request extension `{}`, insert it in a middleware first",
+                type_name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractExtensionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_typed_value() {
+        let mut keys = Keys::new();
+        keys.insert("user_id".to_string(), Box::new(7u64));
+        assert_eq!(get::<u64>(&keys, "user_id"), Some(&7));
+        //类型不匹配或key不存在
+        assert_eq!(get::<String>(&keys, "user_id"), None);
+        assert_eq!(get::<u64>(&keys, "tenant"), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fmt::Display, str::FromStr};
+
+use hyper::{body::Bytes, header, Body, Request};
+use percent_encoding::percent_decode;
+use serde::de::{DeserializeOwned, Error};
+
+use crate::BoxErr;
+
+use super::body::ExtractBodyError;
+
+//请求体是否为application/x-www-form-urlencoded表单
+pub(crate) fn is_form(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.trim_start()
+                .starts_with("application/x-www-form-urlencoded")
+        })
+        .unwrap_or(false)
+}
+
+//解析表单：保留全部键值对（包括重复的key）
+pub(crate) fn parse_pairs(bytes: &Bytes) -> Result<Vec<(String, String)>, ExtractFormError> {
+    check_utf8(bytes)?;
+    serde_urlencoded::from_bytes(bytes).map_err(ExtractFormError::InvalidForm)
+}
+
+pub(crate) fn form<T>(pairs: &[(String, String)], name: &str) -> Result<T, ExtractFormError>
+where
+    T: FromStr,
+    T::Err: Into<BoxErr>,
+{
+    if let Some((_, value)) = pairs.iter().find(|(k, _)| k == name) {
+        parse_value(name, value)
+    } else {
+        Err(ExtractFormError::MissingFormField {
+            name: name.to_string(),
+        })
+    }
+}
+
+//重复的key：a=1&a=2 => [1,2]
+pub(crate) fn form_array<T>(
+    pairs: &[(String, String)],
+    name: &str,
+) -> Result<Vec<T>, ExtractFormError>
+where
+    T: FromStr,
+    T::Err: Into<BoxErr>,
+{
+    pairs
+        .iter()
+        .filter(|(k, _)| k == name)
+        .map(|(_, v)| parse_value(name, v))
+        .collect()
+}
+
+pub(crate) fn bind_form<T>(bytes: &Bytes) -> Result<T, ExtractFormError>
+where
+    T: DeserializeOwned,
+{
+    check_utf8(bytes)?;
+    serde_urlencoded::from_bytes(bytes).map_err(ExtractFormError::InvalidForm)
+}
+
+//serde_urlencoded会把非法的UTF-8替换为U+FFFD，这里提前拒绝，避免静默篡改表单内容
+fn check_utf8(bytes: &Bytes) -> Result<(), ExtractFormError> {
+    percent_decode(bytes)
+        .decode_utf8()
+        .map(|_| ())
+        .map_err(|e| ExtractFormError::InvalidForm(Error::custom(e)))
+}
+
+fn parse_value<T>(name: &str, value: &str) -> Result<T, ExtractFormError>
+where
+    T: FromStr,
+    T::Err: Into<BoxErr>,
+{
+    value
+        .parse::<T>()
+        .map_err(|e| ExtractFormError::InvalidFormValue {
+            name: name.to_string(),
+            source: e.into(),
+        })
+}
+
+#[derive(Debug)]
+pub enum ExtractFormError {
+    MissingFormField { name: String },
+    InvalidFormValue { name: String, source: BoxErr },
+    InvalidBody(ExtractBodyError),
+    InvalidForm(serde::de::value::Error),
+}
+
+impl Display for ExtractFormError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractFormError::MissingFormField { name } => {
+                write!(f, "missing form field:`{}`", name)
+            }
+            ExtractFormError::InvalidFormValue { name, source: _ } => {
+                write!(f, "invalid form field `{}`", name)
+            }
+            ExtractFormError::InvalidBody(e) => write!(f, "{}", e),
+            ExtractFormError::InvalidForm(e) => {
+                write!(f, "failed to deserialize form body({})", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractFormError {}
@@ -1,6 +1,20 @@
 mod comtext;
+mod body;
+mod form;
 mod header;
 mod query;
 mod param;
+mod state;
+mod extension;
 pub mod defer;
-pub use comtext::Context;
\ No newline at end of file
+pub mod multipart;
+pub use comtext::Context;
+pub use body::ExtractBodyError;
+pub use form::ExtractFormError;
+pub use header::ExtractHeaderError;
+pub use param::ExtractParamError;
+pub use query::ExtractQueryError;
+pub use state::ExtractStateError;
+pub use extension::ExtractExtensionError;
+pub(crate) use state::States;
+pub(crate) use extension::Keys;
@@ -0,0 +1,484 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{body::Bytes, header, Body, Request};
+use multer::{Constraints, SizeLimit};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::BoxErr;
+
+//multipart/form-data解析配置：字段数量、单个文件大小、请求体总大小限制，以及大文件落盘
+#[derive(Debug, Clone)]
+pub struct MultipartConfig {
+    max_fields: usize,
+    max_file_size: u64,
+    max_total_size: u64,
+    //超过spool_threshold的文件写入临时目录，None则全部保存在内存中
+    spool_dir: Option<PathBuf>,
+    spool_threshold: usize,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        Self {
+            max_fields: 128,
+            max_file_size: 10 << 20,
+            max_total_size: 32 << 20,
+            spool_dir: None,
+            spool_threshold: 1 << 20,
+        }
+    }
+}
+
+impl MultipartConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = max_fields;
+        self
+    }
+
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    pub fn max_total_size(mut self, max_total_size: u64) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+
+    //文件超过threshold字节时写入dir目录下的临时文件
+    pub fn spool_to<P>(mut self, dir: P, threshold: usize) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.spool_dir = Some(dir.into());
+        self.spool_threshold = threshold;
+        self
+    }
+
+    //使用系统临时目录
+    pub fn spool(self, threshold: usize) -> Self {
+        self.spool_to(std::env::temp_dir(), threshold)
+    }
+}
+
+pub(crate) fn multipart(
+    req: &Request<Body>,
+    body: Body,
+    config: MultipartConfig,
+) -> Result<Multipart, ExtractMultipartError> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(ExtractMultipartError::NotMultipart)?;
+    let boundary = multer::parse_boundary(content_type).map_err(ExtractMultipartError::from)?;
+    let constraints = Constraints::new().size_limit(
+        SizeLimit::new()
+            .whole_stream(config.max_total_size)
+            .per_field(config.max_file_size),
+    );
+    Ok(Multipart {
+        inner: multer::Multipart::with_constraints(body, boundary, constraints),
+        config,
+        fields: 0,
+    })
+}
+
+//multipart请求体：按顺序以流的方式读取每一个字段
+pub struct Multipart {
+    inner: multer::Multipart<'static>,
+    config: MultipartConfig,
+    fields: usize,
+}
+
+impl Multipart {
+    //读取下一个字段，读取新字段前，上一个字段需已读取完或被释放
+    pub async fn next_field(&mut self) -> Result<Option<Field>, ExtractMultipartError> {
+        let field = self.inner.next_field().await?;
+        if field.is_some() {
+            self.fields += 1;
+            if self.fields > self.config.max_fields {
+                return Err(ExtractMultipartError::TooManyFields {
+                    limit: self.config.max_fields,
+                });
+            }
+        }
+        Ok(field.map(|inner| Field {
+            inner,
+            spool_dir: self.config.spool_dir.clone(),
+            spool_threshold: self.config.spool_threshold,
+        }))
+    }
+}
+
+pub struct Field {
+    inner: multer::Field<'static>,
+    spool_dir: Option<PathBuf>,
+    spool_threshold: usize,
+}
+
+impl Field {
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.inner.file_name()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.inner.content_type().map(|m| m.as_ref())
+    }
+
+    //是否为文件字段
+    pub fn is_file(&self) -> bool {
+        self.inner.file_name().is_some()
+    }
+
+    //流式读取字段内容，读取完返回None
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, ExtractMultipartError> {
+        Ok(self.inner.chunk().await?)
+    }
+
+    pub async fn bytes(self) -> Result<Bytes, ExtractMultipartError> {
+        Ok(self.inner.bytes().await?)
+    }
+
+    pub async fn text(self) -> Result<String, ExtractMultipartError> {
+        Ok(self.inner.text().await?)
+    }
+
+    //读取上传文件：未配置落盘或文件较小时保存在内存中，否则写入临时文件
+    pub async fn file(mut self) -> Result<UploadFile, ExtractMultipartError> {
+        let name = self.name().map(String::from);
+        let file_name = self.file_name().map(String::from);
+        let content_type = self.content_type().map(String::from);
+        let mut size = 0;
+        let mut buf = Vec::new();
+        let mut spooled: Option<(SpooledFile, fs::File)> = None;
+        while let Some(chunk) = self.chunk().await? {
+            size += chunk.len() as u64;
+            if let Some((_, file)) = spooled.as_mut() {
+                file.write_all(&chunk).await?;
+                continue;
+            }
+            buf.extend_from_slice(&chunk);
+            if let Some(dir) = &self.spool_dir {
+                if buf.len() > self.spool_threshold {
+                    let (tmp, mut file) = SpooledFile::create(dir).await?;
+                    file.write_all(&buf).await?;
+                    buf = Vec::new();
+                    spooled = Some((tmp, file));
+                }
+            }
+        }
+        let data = match spooled {
+            Some((tmp, mut file)) => {
+                file.flush().await?;
+                FileData::Spooled(tmp)
+            }
+            None => FileData::Memory(Bytes::from(buf)),
+        };
+        Ok(UploadFile {
+            name,
+            file_name,
+            content_type,
+            size,
+            data,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct UploadFile {
+    pub name: Option<String>,
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub size: u64,
+    pub data: FileData,
+}
+
+impl UploadFile {
+    //保存上传文件到指定路径
+    pub async fn save<P>(self, path: P) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        match self.data {
+            FileData::Memory(bytes) => fs::write(path, bytes).await,
+            FileData::Spooled(tmp) => tmp.persist(path).await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FileData {
+    Memory(Bytes),
+    Spooled(SpooledFile),
+}
+
+//落盘的临时文件，未persist时在drop时删除
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: Option<PathBuf>,
+}
+
+static SPOOL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+impl SpooledFile {
+    async fn create(dir: &Path) -> std::io::Result<(SpooledFile, fs::File)> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "rdd-web-{}-{}-{}.upload",
+            std::process::id(),
+            nanos,
+            SPOOL_SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await?;
+        Ok((SpooledFile { path: Some(path) }, file))
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().unwrap()
+    }
+
+    //移动临时文件到指定路径，移动后不再自动删除
+    pub async fn persist<P>(mut self, to: P) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let from = self.path.take().unwrap();
+        if fs::rename(&from, to.as_ref()).await.is_err() {
+            //跨文件系统时rename会失败，改为复制
+            fs::copy(&from, to.as_ref()).await?;
+            fs::remove_file(&from).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtractMultipartError {
+    NotMultipart,
+    InvalidBoundary,
+    TooManyFields {
+        limit: usize,
+    },
+    FileTooLarge {
+        name: Option<String>,
+        limit: u64,
+    },
+    PayloadTooLarge {
+        limit: u64,
+    },
+    Malformed {
+        source: BoxErr,
+    },
+    Io {
+        source: std::io::Error,
+    },
+}
+
+impl From<multer::Error> for ExtractMultipartError {
+    fn from(e: multer::Error) -> Self {
+        match e {
+            multer::Error::NoMultipart | multer::Error::DecodeContentType(_) => {
+                ExtractMultipartError::NotMultipart
+            }
+            multer::Error::NoBoundary => ExtractMultipartError::InvalidBoundary,
+            multer::Error::FieldSizeExceeded { limit, field_name } => {
+                ExtractMultipartError::FileTooLarge {
+                    name: field_name,
+                    limit,
+                }
+            }
+            multer::Error::StreamSizeExceeded { limit } => {
+                ExtractMultipartError::PayloadTooLarge { limit }
+            }
+            //超出请求体大小限制时multer会将错误包装为StreamReadFailed
+            multer::Error::StreamReadFailed(source) => match source.downcast::<multer::Error>() {
+                Ok(e) => ExtractMultipartError::from(*e),
+                Err(source) => ExtractMultipartError::Malformed { source },
+            },
+            e => ExtractMultipartError::Malformed { source: e.into() },
+        }
+    }
+}
+
+impl From<std::io::Error> for ExtractMultipartError {
+    fn from(e: std::io::Error) -> Self {
+        ExtractMultipartError::Io { source: e }
+    }
+}
+
+impl Display for ExtractMultipartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractMultipartError::NotMultipart => {
+                write!(f, "request content type is not multipart/form-data")
+            }
+            ExtractMultipartError::InvalidBoundary => {
+                write!(f, "missing or invalid multipart boundary")
+            }
+            ExtractMultipartError::TooManyFields { limit } => {
+                write!(f, "too many multipart fields, limit:{}", limit)
+            }
+            ExtractMultipartError::FileTooLarge { name, limit } => write!(
+                f,
+                "multipart field `{}` exceeds size limit:{}",
+                name.as_deref().unwrap_or_default(),
+                limit
+            ),
+            ExtractMultipartError::PayloadTooLarge { limit } => {
+                write!(f, "multipart body exceeds size limit:{}", limit)
+            }
+            ExtractMultipartError::Malformed { source } => {
+                write!(f, "malformed multipart body({})", source)
+            }
+            ExtractMultipartError::Io { source } => {
+                write!(f, "failed to save multipart file({})", source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractMultipartError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "X-RDD-BOUNDARY";
+
+    fn build_request(body: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(Body::from(body.replace('\n', "\r\n")))
+            .unwrap()
+    }
+
+    fn upload_body() -> String {
+        format!(
+            "--{b}\nContent-Disposition: form-data; name=\"title\"\n\nhello\n\
+             --{b}\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a.txt\"\n\
+             Content-Type: text/plain\n\n0123456789\n--{b}--\n",
+            b = BOUNDARY
+        )
+    }
+
+    async fn parse(
+        config: MultipartConfig,
+    ) -> Result<Vec<(String, UploadFile)>, ExtractMultipartError> {
+        let mut req = build_request(&upload_body());
+        let body = std::mem::take(req.body_mut());
+        let mut multipart = multipart(&req, body, config)?;
+        let mut fields = Vec::new();
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.name().unwrap().to_string();
+            fields.push((name, field.file().await?));
+        }
+        Ok(fields)
+    }
+
+    #[tokio::test]
+    async fn test_multipart_fields_in_memory() {
+        let fields = parse(MultipartConfig::new()).await.unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].0, "title");
+        assert!(matches!(&fields[0].1.data, FileData::Memory(b) if b == "hello"));
+        let avatar = &fields[1].1;
+        assert_eq!(avatar.file_name.as_deref(), Some("a.txt"));
+        assert_eq!(avatar.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(avatar.size, 10);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_spool_large_file() {
+        let dir = std::env::temp_dir();
+        let mut fields = parse(MultipartConfig::new().spool_to(&dir, 4))
+            .await
+            .unwrap();
+        let (_, avatar) = fields.pop().unwrap();
+        let path = match &avatar.data {
+            FileData::Spooled(tmp) => tmp.path().to_path_buf(),
+            FileData::Memory(_) => panic!("file should be spooled"),
+        };
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        drop(avatar);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_limits() {
+        let err = parse(MultipartConfig::new().max_fields(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExtractMultipartError::TooManyFields { limit: 1 }
+        ));
+
+        let err = parse(MultipartConfig::new().max_file_size(5))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExtractMultipartError::FileTooLarge { limit: 5, .. }
+        ));
+
+        let err = parse(MultipartConfig::new().max_total_size(16))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExtractMultipartError::PayloadTooLarge { limit: 16 }
+        ));
+    }
+
+    #[test]
+    fn test_multipart_invalid_content_type() {
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, "multipart/form-data")
+            .body(Body::empty())
+            .unwrap();
+        let err = multipart(&req, Body::empty(), MultipartConfig::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ExtractMultipartError::InvalidBoundary));
+
+        let req = Request::builder().body(Body::empty()).unwrap();
+        let err = multipart(&req, Body::empty(), MultipartConfig::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ExtractMultipartError::NotMultipart));
+    }
+}
@@ -1,8 +1,11 @@
 use std::{collections::HashMap, fmt::Display, str::FromStr};
 
+use serde::de::DeserializeOwned;
 
 use crate::BoxErr;
 
+use super::query::QueryNode;
+
 pub(crate) fn param<T>(params: &HashMap<String, String>, name: &str) -> Result<T, ExtractParamError>
 where
     T: FromStr,
@@ -22,10 +25,19 @@ where
     }
 }
 
+//按路由中参数的顺序反序列化：/user/:id => u64，/user/:id/:name => (u64,String)或结构体
+pub(crate) fn path<T>(pairs: &[(String, String)]) -> Result<T, ExtractParamError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(QueryNode::from_params(pairs)).map_err(ExtractParamError::InvalidParams)
+}
+
 #[derive(Debug)]
 pub enum ExtractParamError {
     MissingParamName { name: String },
     InvalidParamValue { name: String, source: BoxErr },
+    InvalidParams(serde::de::value::Error),
 }
 
 impl Display for ExtractParamError {
@@ -37,6 +49,9 @@ impl Display for ExtractParamError {
             ExtractParamError::MissingParamName { name } => {
                 write!(f, "missing param value:`{}`", name)
             }
+            ExtractParamError::InvalidParams(e) => {
+                write!(f, "failed to deserialize path params({})", e)
+            }
         }
     }
 }
@@ -1,22 +1,526 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use hyper::{Body, Request};
-use serde::Deserialize;
+use serde::{
+    de::{
+        self,
+        value::{Error, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any,
+};
 
-pub(crate) fn query<'de, T>(req: &'de Request<Body>) -> Result<T, ExtractQueryError>
+use crate::BoxErr;
+
+//嵌套key的最大深度：filter[a][b]...超出部分按原样作为key
+const MAX_DEPTH: usize = 8;
+
+//解析查询字符串，支持：
+//1）重复的key：tag=a&tag=b => tag:[a,b]
+//2）数组key：tag[]=a&tag[]=b => tag:[a,b]
+//3）嵌套key：filter[status]=x&filter[page][size]=10 => filter:{status:x,page:{size:10}}
+pub(crate) fn query<T>(req: &Request<Body>) -> Result<T, ExtractQueryError>
+where
+    T: DeserializeOwned,
+{
+    let pairs = pairs(req);
+    let mut root = QueryNode::default();
+    for (key, value) in pairs {
+        root.insert(&split_key(&key), value);
+    }
+    T::deserialize(root).map_err(ExtractQueryError::InvalidQuery)
+}
+
+pub(crate) fn query_value<T>(req: &Request<Body>, name: &str) -> Result<T, ExtractQueryError>
 where
-    T: Deserialize<'de>,
+    T: FromStr,
+    T::Err: Into<BoxErr>,
 {
+    if let Some((_, value)) = pairs(req).into_iter().find(|(k, _)| k == name) {
+        parse_value(name, &value)
+    } else {
+        Err(ExtractQueryError::MissingQueryName {
+            name: name.to_string(),
+        })
+    }
+}
+
+pub(crate) fn query_array<T>(req: &Request<Body>, name: &str) -> Result<Vec<T>, ExtractQueryError>
+where
+    T: FromStr,
+    T::Err: Into<BoxErr>,
+{
+    pairs(req)
+        .into_iter()
+        .filter(|(k, _)| k == name)
+        .map(|(_, v)| parse_value(name, &v))
+        .collect()
+}
+
+fn pairs(req: &Request<Body>) -> Vec<(String, String)> {
     let query = req.uri().query().unwrap_or_default();
-    serde_urlencoded::from_str(query).map_err(|e| ExtractQueryError(e))
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap_or_default()
+}
+
+fn parse_value<T>(name: &str, value: &str) -> Result<T, ExtractQueryError>
+where
+    T: FromStr,
+    T::Err: Into<BoxErr>,
+{
+    value
+        .parse::<T>()
+        .map_err(|e| ExtractQueryError::InvalidQueryValue {
+            name: name.to_string(),
+            source: e.into(),
+        })
+}
+
+//filter[status][x] => [filter,status,x]; tag[] => [tag,""]
+fn split_key(key: &str) -> Vec<&str> {
+    let Some(start) = key.find('[') else {
+        return vec![key];
+    };
+    if start == 0 || !key.ends_with(']') {
+        return vec![key];
+    }
+    let mut parts = vec![&key[..start]];
+    let mut rest = &key[start..];
+    while let Some(inner) = rest.strip_prefix('[') {
+        let Some(end) = inner.find(']') else {
+            return vec![key];
+        };
+        if parts.len() > MAX_DEPTH {
+            return vec![key];
+        }
+        parts.push(&inner[..end]);
+        rest = &inner[end + 1..];
+    }
+    if !rest.is_empty() {
+        return vec![key];
+    }
+    parts
+}
+
+//查询参数树：叶子节点的值保存在values中（重复key会有多个值）
+#[derive(Debug, Default)]
+pub(crate) struct QueryNode {
+    values: Vec<String>,
+    children: Vec<(String, QueryNode)>,
+}
+
+impl QueryNode {
+    //路径参数：只有一个参数时，根节点也保存该值，可直接反序列化为基本类型（如u64）
+    pub(crate) fn from_params(params: &[(String, String)]) -> QueryNode {
+        let mut root = QueryNode::default();
+        if let [(_, value)] = params {
+            root.values.push(value.clone());
+        }
+        for (key, value) in params {
+            root.child_mut(key).values.push(value.clone());
+        }
+        root
+    }
+
+    fn insert(&mut self, path: &[&str], value: String) {
+        match path.split_first() {
+            None => self.values.push(value),
+            //tag[]=a：追加到数组
+            Some((&"", [])) => self.values.push(value),
+            Some((key, rest)) => self.child_mut(key).insert(rest, value),
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> &mut QueryNode {
+        let index = match self.children.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                self.children.push((key.to_string(), QueryNode::default()));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index].1
+    }
+
+    //单值：重复key时取第一个值
+    fn into_value(self) -> Result<ValueDeserializer, Error> {
+        match self.values.into_iter().next() {
+            Some(value) => Ok(ValueDeserializer(value)),
+            None => Err(de::Error::custom(
+                "expected a value, found nested query keys",
+            )),
+        }
+    }
+}
+
+macro_rules! deserialize_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.into_value()?.$method(visitor)
+            }
+        )*
+    };
 }
+
+impl<'de> de::Deserializer<'de> for QueryNode {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.children.is_empty() {
+            return self.deserialize_map(visitor);
+        }
+        match self.values.len() {
+            0 => visitor.visit_unit(),
+            1 => self.into_value()?.deserialize_any(visitor),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.values.is_empty() {
+            return visitor.visit_seq(SeqDeserializer::new(
+                self.values.into_iter().map(ValueDeserializer),
+            ));
+        }
+        //a[0]=x&a[1]=y：按下标排序
+        let mut children = self.children;
+        children.sort_by_key(|(k, _)| k.parse::<usize>().unwrap_or(usize::MAX));
+        visitor.visit_seq(SeqDeserializer::new(
+            children.into_iter().map(|(_, node)| node),
+        ))
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(MapDeserializer::new(self.children.into_iter()))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_value()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_identifier
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 unit unit_struct
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for QueryNode {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+//单个查询参数值：按目标类型解析字符串
+struct ValueDeserializer(String);
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(de::Error::custom(format!("invalid value `{}`", self.0))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::once(self)))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .into_deserializer()
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    deserialize_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
 #[derive(Debug)]
-pub struct ExtractQueryError(pub serde::de::value::Error);
+pub enum ExtractQueryError {
+    MissingQueryName { name: String },
+    InvalidQueryValue { name: String, source: BoxErr },
+    InvalidQuery(Error),
+}
 
 impl Display for ExtractQueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "failed to deserialize query string({})", &self.0)
+        match self {
+            ExtractQueryError::MissingQueryName { name } => {
+                write!(f, "missing query value:`{}`", name)
+            }
+            ExtractQueryError::InvalidQueryValue { name, source: _ } => {
+                write!(f, "invalid query name `{}`", name)
+            }
+            ExtractQueryError::InvalidQuery(e) => {
+                write!(f, "failed to deserialize query string({})", e)
+            }
+        }
     }
 }
 
 impl std::error::Error for ExtractQueryError {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn request(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Active,
+        Closed,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Filter {
+        status: Status,
+        owner: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Paging {
+        page: u32,
+        size: Option<u32>,
+        #[serde(default)]
+        tag: Vec<String>,
+        filter: Option<Filter>,
+    }
+
+    #[test]
+    fn test_query_scalar_and_repeated_keys() {
+        let req = request("/list?page=2&size=10&tag=a&tag=b");
+        let paging: Paging = query(&req).unwrap();
+        assert_eq!(
+            paging,
+            Paging {
+                page: 2,
+                size: Some(10),
+                tag: vec!["a".to_string(), "b".to_string()],
+                filter: None,
+            }
+        );
+
+        let paging: Paging = query(&request("/list?page=1&tag[]=x")).unwrap();
+        assert_eq!(paging.tag, vec!["x".to_string()]);
+        assert_eq!(paging.size, None);
+    }
+
+    #[test]
+    fn test_query_nested_keys() {
+        let req = request("/list?page=1&filter[status]=closed&filter%5Bowner%5D=%E4%BD%A0");
+        let paging: Paging = query(&req).unwrap();
+        assert_eq!(
+            paging.filter,
+            Some(Filter {
+                status: Status::Closed,
+                owner: Some("你".to_string()),
+            })
+        );
+
+        let map: HashMap<String, HashMap<String, Vec<u8>>> =
+            query(&request("/?a[x][0]=1&a[x][1]=2&a[y][]=3")).unwrap();
+        assert_eq!(map["a"]["x"], vec![1, 2]);
+        assert_eq!(map["a"]["y"], vec![3]);
+    }
+
+    #[test]
+    fn test_query_invalid() {
+        let err = query::<Paging>(&request("/list?page=abc")).unwrap_err();
+        assert!(matches!(err, ExtractQueryError::InvalidQuery(_)));
+        let err = query::<Paging>(&request("/list")).unwrap_err();
+        assert!(matches!(err, ExtractQueryError::InvalidQuery(_)));
+    }
+
+    #[test]
+    fn test_query_value() {
+        let req = request("/list?page=2&tag=a&tag=b&filter[status]=active");
+        assert_eq!(query_value::<u32>(&req, "page").unwrap(), 2);
+        assert_eq!(
+            query_value::<String>(&req, "filter[status]").unwrap(),
+            "active"
+        );
+        assert_eq!(query_array::<String>(&req, "tag").unwrap(), vec!["a", "b"]);
+        assert!(matches!(
+            query_value::<u32>(&req, "size"),
+            Err(ExtractQueryError::MissingQueryName { .. })
+        ));
+        assert!(matches!(
+            query_value::<u32>(&req, "tag"),
+            Err(ExtractQueryError::InvalidQueryValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("a"), vec!["a"]);
+        assert_eq!(split_key("a[b][c]"), vec!["a", "b", "c"]);
+        assert_eq!(split_key("a[]"), vec!["a", ""]);
+        assert_eq!(split_key("a[b"), vec!["a[b"]);
+        assert_eq!(split_key("[a]"), vec!["[a]"]);
+        assert_eq!(split_key("a[b]c"), vec!["a[b]c"]);
+    }
+}
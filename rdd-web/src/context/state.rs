@@ -0,0 +1,40 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt::Display,
+    sync::Arc,
+};
+
+//应用级共享状态：按类型保存，每种类型只保存一份
+pub(crate) type States = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+pub(crate) fn state<T>(states: &States) -> Result<Arc<T>, ExtractStateError>
+where
+    T: Send + Sync + 'static,
+{
+    states
+        .get(&TypeId::of::<T>())
+        .and_then(|state| state.clone().downcast::<T>().ok())
+        .ok_or(ExtractStateError::MissingState {
+            type_name: type_name::<T>(),
+        })
+}
+
+#[derive(Debug)]
+pub enum ExtractStateError {
+    MissingState { type_name: &'static str },
+}
+
+impl Display for ExtractStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractStateError::MissingState { type_name } => write!(
+                f,
+                "missing application state `{}`, register it with Engine::with_state",
+                type_name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractStateError {}
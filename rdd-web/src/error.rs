@@ -0,0 +1,252 @@
+use std::fmt::Display;
+
+use hyper::{Body, Response, StatusCode};
+use log::error;
+use serde_json::json;
+
+use crate::{
+    context::{
+        multipart::ExtractMultipartError, ExtractBodyError, ExtractExtensionError,
+        ExtractFormError, ExtractHeaderError, ExtractParamError, ExtractQueryError,
+        ExtractStateError,
+    },
+    validate::ValidationErrors,
+    BoxErr, Context, IntoResponse, Json, UrlForError,
+};
+
+//框架统一错误：参数提取、请求体、渲染、路由等失败，每种错误对应一个http状态码
+//handler可以直接返回Result<T, Error>，各Extract*Error可以通过`?`转换为Error
+#[derive(Debug)]
+pub enum Error {
+    Param(ExtractParamError),
+    Query(ExtractQueryError),
+    Header(ExtractHeaderError),
+    Body(ExtractBodyError),
+    Form(ExtractFormError),
+    Multipart(ExtractMultipartError),
+    State(ExtractStateError),
+    Extension(ExtractExtensionError),
+    //绑定数据校验失败
+    Validation(ValidationErrors),
+    //命名路由生成url失败
+    UrlFor(UrlForError),
+    //响应序列化失败
+    Render { source: BoxErr },
+    NotFound,
+    MethodNotAllowed,
+    //其他业务错误
+    Internal { source: BoxErr },
+}
+
+//自定义错误渲染：Engine::error_renderer注册
+pub(crate) type ErrorRenderer = dyn Fn(&Context, &Error) -> Response<Body> + Send + Sync + 'static;
+
+impl Error {
+    pub fn internal<E>(source: E) -> Self
+    where
+        E: Into<BoxErr>,
+    {
+        Error::Internal {
+            source: source.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Body(ExtractBodyError::PayloadTooLarge { .. })
+            | Error::Form(ExtractFormError::InvalidBody(ExtractBodyError::PayloadTooLarge {
+                ..
+            })) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::Param(_)
+            | Error::Query(_)
+            | Error::Header(_)
+            | Error::Body(_)
+            | Error::Form(_) => StatusCode::BAD_REQUEST,
+            Error::Multipart(e) => match e {
+                ExtractMultipartError::NotMultipart => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ExtractMultipartError::TooManyFields { .. }
+                | ExtractMultipartError::FileTooLarge { .. }
+                | ExtractMultipartError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+                ExtractMultipartError::Io { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            },
+            Error::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            Error::State(_)
+            | Error::Extension(_)
+            | Error::UrlFor(_)
+            | Error::Render { .. }
+            | Error::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Param(e) => e.fmt(f),
+            Error::Query(e) => e.fmt(f),
+            Error::Header(e) => e.fmt(f),
+            Error::Body(e) => e.fmt(f),
+            Error::Form(e) => e.fmt(f),
+            Error::Multipart(e) => e.fmt(f),
+            Error::State(e) => e.fmt(f),
+            Error::Extension(e) => e.fmt(f),
+            Error::Validation(e) => e.fmt(f),
+            Error::UrlFor(e) => e.fmt(f),
+            Error::Render { source } => write!(f, "failed to render response({})", source),
+            Error::NotFound => write!(f, "404 not found"),
+            Error::MethodNotAllowed => write!(f, "405 method not allowed"),
+            Error::Internal { source } => source.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Param(e) => Some(e),
+            Error::Query(e) => Some(e),
+            Error::Header(e) => Some(e),
+            Error::Body(e) => Some(e),
+            Error::Form(e) => Some(e),
+            Error::Multipart(e) => Some(e),
+            Error::State(e) => Some(e),
+            Error::Extension(e) => Some(e),
+            Error::Validation(e) => Some(e),
+            Error::UrlFor(e) => Some(e),
+            Error::Render { source } | Error::Internal { source } => Some(source.as_ref()),
+            Error::NotFound | Error::MethodNotAllowed => None,
+        }
+    }
+}
+
+macro_rules! impl_from_error {
+    ($($variant:ident($err:ty)),*) => {
+        $(
+            impl From<$err> for Error {
+                fn from(e: $err) -> Self {
+                    Error::$variant(e)
+                }
+            }
+
+            impl IntoResponse for $err {
+                fn into_response(self) -> Response<Body> {
+                    Error::from(self).into_response()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_error!(
+    Param(ExtractParamError),
+    Query(ExtractQueryError),
+    Header(ExtractHeaderError),
+    Body(ExtractBodyError),
+    Form(ExtractFormError),
+    Multipart(ExtractMultipartError),
+    State(ExtractStateError),
+    Extension(ExtractExtensionError),
+    Validation(ValidationErrors),
+    UrlFor(UrlForError)
+);
+
+//默认渲染为纯文本（校验错误为json），错误本身保存在响应扩展中，写入上下文时交给自定义渲染器处理
+//所有5xx错误只返回通用信息，错误详情（可能包含数据库地址、类型路径、文件路径等）写入日志
+impl IntoResponse for Error {
+    fn into_response(self) -> Response<Body> {
+        let mut resp = match &self {
+            _ if self.status().is_server_error() => {
+                error!("服务端错误:{}", self);
+                let status = self.status();
+                let reason = status.canonical_reason().unwrap_or_default();
+                let body = format!("{} {}", status.as_u16(), reason.to_lowercase());
+                (status, body).into_response()
+            }
+            Error::Validation(e) => {
+                let errors: Vec<_> = e
+                    .errors()
+                    .iter()
+                    .map(|e| json!({ "field": e.field, "message": e.message }))
+                    .collect();
+                (self.status(), Json(json!({ "errors": errors }))).into_response()
+            }
+            _ => (self.status(), self.to_string()).into_response(),
+        };
+        resp.extensions_mut().insert(self);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status() {
+        let e = Error::from(ExtractParamError::MissingParamName {
+            name: "id".to_string(),
+        });
+        assert_eq!(e.status(), StatusCode::BAD_REQUEST);
+        let e = Error::from(ExtractMultipartError::NotMultipart);
+        assert_eq!(e.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let e = Error::from(ExtractMultipartError::PayloadTooLarge { limit: 1 });
+        assert_eq!(e.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let e = Error::from(ExtractBodyError::PayloadTooLarge { limit: 1 });
+        assert_eq!(e.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let e = Error::from(ExtractFormError::InvalidBody(
+            ExtractBodyError::PayloadTooLarge { limit: 1 },
+        ));
+        assert_eq!(e.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(Error::NotFound.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            Error::MethodNotAllowed.status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            Error::internal("db down").status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_error_into_response() {
+        let resp = Error::from(ExtractBodyError::MissingBody).into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(resp.extensions().get::<Error>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_internal_error_hides_source() {
+        let resp = Error::internal("password=hunter2 db down").into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        //自定义渲染器仍能读取原始错误
+        let e = resp.extensions().get::<Error>().unwrap();
+        assert_eq!(e.to_string(), "password=hunter2 db down");
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"500 internal server error");
+
+        let resp = Error::from(ExtractStateError::MissingState {
+            type_name: "alloc::string::String",
+        })
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"500 internal server error");
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_response() {
+        let mut errors = ValidationErrors::new();
+        errors.add("age", "must be between 1 and 150");
+        let resp = Error::from(errors).into_response();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(
+            &body[..],
+            br#"{"errors":[{"field":"age","message":"must be between 1 and 150"}]}"#
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{convert::Infallible, ops::Deref, sync::Arc};
+
+use hyper::{HeaderMap, Method, Uri};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    context::{
+        ExtractBodyError, ExtractExtensionError, ExtractFormError, ExtractParamError,
+        ExtractQueryError, ExtractStateError,
+    },
+    router::BoxFuture,
+    validate::{Validate, ValidationErrors},
+    Context, Error, IntoResponse,
+};
+
+//提取器：从请求上下文中提取handler参数，如：
+//async fn get_user(Path(id): Path<u64>, Query(q): Query<Paging>) -> Json<User>
+//提取失败时返回Rejection对应的响应（参数错误为400），不再执行handler
+pub trait FromContext: Sized + Send {
+    type Rejection: IntoResponse + Send;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>>;
+}
+
+//路径参数：/user/:id
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Path<T>(pub T);
+
+//查询参数：?page=1&size=10
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+//json请求体，同时也可以作为响应
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+//application/x-www-form-urlencoded请求体
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+//通过Engine::with_state注册的应用级共享状态
+#[derive(Debug, Default)]
+pub struct State<T>(pub Arc<T>);
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        State(self.0.clone())
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+//中间件通过c.extensions_mut().insert写入的请求级扩展（克隆一份）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Extension<T>(pub T);
+
+impl<T> Deref for Extension<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+//绑定后执行校验：Valid<Json<T>>、Valid<Query<T>>、Valid<Form<T>>、Valid<Path<T>>，T需实现Validate
+//校验失败返回422，列出全部字段错误
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Valid<T>(pub T);
+
+impl<T> Deref for Valid<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> FromContext for Path<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = ExtractParamError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { c.bind_path().map(Path) })
+    }
+}
+
+impl<T> FromContext for Query<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = ExtractQueryError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { c.bind_query().map(Query) })
+    }
+}
+
+impl<T> FromContext for Json<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = ExtractBodyError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { c.bind_json().await.map(Json) })
+    }
+}
+
+impl<T> FromContext for Form<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = ExtractFormError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { c.bind_form().await.map(Form) })
+    }
+}
+
+impl<T> FromContext for State<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Rejection = ExtractStateError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { c.state().map(State) })
+    }
+}
+
+impl<T> FromContext for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Rejection = ExtractExtensionError;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            c.extensions()
+                .get::<T>()
+                .cloned()
+                .map(Extension)
+                .ok_or_else(ExtractExtensionError::missing::<T>)
+        })
+    }
+}
+
+impl<E> FromContext for Valid<E>
+where
+    E: FromContext + Validate,
+    E::Rejection: Into<Error>,
+{
+    type Rejection = Error;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            let value = E::from_context(c).await.map_err(Into::into)?;
+            value.validate()?;
+            Ok(Valid(value))
+        })
+    }
+}
+
+impl FromContext for HeaderMap {
+    type Rejection = Infallible;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(c.request.headers().clone()) })
+    }
+}
+
+impl FromContext for Method {
+    type Rejection = Infallible;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(c.request.method().clone()) })
+    }
+}
+
+impl FromContext for Uri {
+    type Rejection = Infallible;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(c.request.uri().clone()) })
+    }
+}
+
+//可选参数：提取失败时为None
+impl<T> FromContext for Option<T>
+where
+    T: FromContext,
+{
+    type Rejection = Infallible;
+
+    fn from_context(c: &mut Context) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(T::from_context(c).await.ok()) })
+    }
+}
+
+macro_rules! impl_validate {
+    ($($extractor:ident),*) => {
+        $(
+            impl<T> Validate for $extractor<T>
+            where
+                T: Validate,
+            {
+                fn validate(&self) -> Result<(), ValidationErrors> {
+                    self.0.validate()
+                }
+            }
+        )*
+    };
+}
+
+impl_validate!(Path, Query, Json, Form);
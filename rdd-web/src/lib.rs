@@ -1,12 +1,31 @@
-pub type BoxErr = Box<dyn std::error::Error>;
+pub type BoxErr = Box<dyn std::error::Error + Send + Sync>;
+
+pub use hyper::{Body, Response, StatusCode};
 
 mod router;
-pub use router::RouterGroup;
+pub use router::{
+    from_fn, BoxFuture, HandlerFn, IntoHandler, RouteError, RouteInfo, RouterGroup, UrlForError,
+};
 
 mod context;
-pub use context::Context;
+pub use context::{
+    defer, multipart, Context, ExtractBodyError, ExtractExtensionError, ExtractFormError,
+    ExtractHeaderError, ExtractParamError, ExtractQueryError, ExtractStateError,
+};
+
+pub mod extract;
+pub use extract::{Extension, FromContext, Json, State, Valid};
+
+pub mod response;
+pub use response::{Html, IntoResponse};
+
+pub mod validate;
+pub use validate::{Validate, ValidationErrors};
+
+mod error;
+pub use error::Error;
 
 mod server;
-pub use server::{Server,Engine,default,new};
+pub use server::{Server,Engine,PathPolicy,default,new};
 
-pub mod middleware;
\ No newline at end of file
+pub mod middleware;
@@ -1,18 +1,44 @@
-use crate::Context;
-use log::{debug, error, info};
-use std::panic::{self, AssertUnwindSafe};
+use crate::{response::write_response, router::BoxFuture, Context, Error, IntoResponse};
+use log::debug;
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context as TaskContext, Poll},
+};
 
-pub fn recovery(c: &mut Context) {
-    //此处这样应该不行，对于panic问题应该结合中间件相关组件一起考虑，暂时先这样
-    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+pub async fn recovery(c: &mut Context) {
+    //捕获下游handler（包括异步执行过程中）发生的panic
+    let result = CatchUnwind(Box::pin(async {
         debug!("recovery");
-        c.next();
-    }));
-    match result {
-        Ok(_) => {}
-        Err(e) => {
-            error!("系统出错");
-            c.string(None, "系统开小差");
+        c.next().await;
+    }))
+    .await;
+    //返回500（经过自定义错误渲染器），panic信息只写入日志
+    if let Err(payload) = result {
+        //panic可能发生在尚未调用next的中间件中，需结束调用链，避免外层继续执行下游handler
+        c.done();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        let resp = Error::internal(format!("handler panicked: {}", message)).into_response();
+        write_response(c, resp);
+    }
+}
+
+//在每次poll时捕获panic的future包装
+struct CatchUnwind<'a>(BoxFuture<'a, ()>);
+
+impl Future for CatchUnwind<'_> {
+    type Output = std::thread::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.0.as_mut().poll(cx))) {
+            Ok(Poll::Ready(())) => Poll::Ready(Ok(())),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
         }
     }
 }
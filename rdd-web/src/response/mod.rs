@@ -0,0 +1,153 @@
+use std::convert::Infallible;
+
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderValue},
+    Body, Response, StatusCode,
+};
+use serde::Serialize;
+
+use crate::{extract::Json, Context, Error};
+
+//handler返回值转换为http响应，如：
+//String,&'static str,Json<T>,Html<T>,StatusCode,(StatusCode, T),Result<T, E>,Response<Body>
+pub trait IntoResponse {
+    fn into_response(self) -> Response<Body>;
+}
+
+//html响应
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Html<T>(pub T);
+
+//handler返回()时不修改上下文中的响应：兼容通过c.json/c.string写响应的handler与中间件
+#[derive(Clone, Copy)]
+struct KeepResponse;
+
+impl IntoResponse for () {
+    fn into_response(self) -> Response<Body> {
+        let mut resp = Response::new(Body::empty());
+        resp.extensions_mut().insert(KeepResponse);
+        resp
+    }
+}
+
+impl IntoResponse for Infallible {
+    fn into_response(self) -> Response<Body> {
+        match self {}
+    }
+}
+
+impl IntoResponse for Response<Body> {
+    fn into_response(self) -> Response<Body> {
+        self
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response<Body> {
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = self;
+        resp
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response<Body> {
+        with_content_type(Body::from(self), "text/plain; charset=utf-8")
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response<Body> {
+        with_content_type(Body::from(self), "text/plain; charset=utf-8")
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self) -> Response<Body> {
+        with_content_type(Body::from(self), "application/octet-stream")
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> Response<Body> {
+        with_content_type(Body::from(self), "application/octet-stream")
+    }
+}
+
+impl<T> IntoResponse for Html<T>
+where
+    T: Into<Body>,
+{
+    fn into_response(self) -> Response<Body> {
+        with_content_type(self.0.into(), "text/html; charset=utf-8")
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response<Body> {
+        match serde_json::to_vec(&self.0) {
+            Ok(data) => with_content_type(Body::from(data), "application/json; charset=utf-8"),
+            Err(e) => Error::Render { source: e.into() }.into_response(),
+        }
+    }
+}
+
+impl<T> IntoResponse for (StatusCode, T)
+where
+    T: IntoResponse,
+{
+    fn into_response(self) -> Response<Body> {
+        let mut resp = self.1.into_response();
+        *resp.status_mut() = self.0;
+        //状态码需要生效，不能再保留上下文中的响应
+        resp.extensions_mut().remove::<KeepResponse>();
+        resp
+    }
+}
+
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: IntoResponse,
+{
+    fn into_response(self) -> Response<Body> {
+        match self {
+            Ok(v) => v.into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
+
+fn with_content_type(body: Body, content_type: &'static str) -> Response<Body> {
+    let mut resp = Response::new(body);
+    resp.headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp
+}
+
+//写入handler返回的响应：保留上游中间件已设置（且未被覆盖）的响应头
+//错误响应优先交给Engine上注册的错误渲染器
+pub(crate) fn write_response(c: &mut Context, mut resp: Response<Body>) {
+    if resp.extensions().get::<KeepResponse>().is_some() {
+        return;
+    }
+    if let Some(e) = resp.extensions_mut().remove::<Error>() {
+        if let Some(renderer) = c.error_renderer() {
+            resp = renderer(c, &e);
+        }
+    }
+    let headers = std::mem::take(c.response.headers_mut());
+    for name in headers.keys() {
+        if resp.headers().contains_key(name) {
+            continue;
+        }
+        for value in headers.get_all(name) {
+            resp.headers_mut().append(name, value.clone());
+        }
+    }
+    c.response = resp;
+}
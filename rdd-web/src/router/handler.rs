@@ -1,3 +1,128 @@
-use crate::context::Context;
+use std::{future::Future, pin::Pin, sync::Arc};
 
-pub type Handler = dyn Fn(&mut Context) + Send + Sync + 'static;
+use crate::{
+    context::Context,
+    extract::FromContext,
+    response::{write_response, IntoResponse},
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+//统一的异步handler：路由handler与中间件都会被包装成该类型
+pub type Handler = dyn for<'a> Fn(&'a mut Context) -> BoxFuture<'a, ()> + Send + Sync + 'static;
+
+//用户可注册的handler：async fn(c: &mut Context)，不捕获外部变量的async闭包，或者from_fn包装的闭包
+//返回值实现IntoResponse即可，如：Result<Json<User>, AppError>
+pub trait HandlerFn<'a>: Send + Sync + 'static {
+    type Output: IntoResponse;
+    type Future: Future<Output = Self::Output> + Send + 'a;
+
+    fn call(&self, c: &'a mut Context) -> Self::Future;
+}
+
+impl<'a, F, Fut> HandlerFn<'a> for F
+where
+    F: Fn(&'a mut Context) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'a,
+    Fut::Output: IntoResponse,
+{
+    type Output = Fut::Output;
+    type Future = Fut;
+
+    fn call(&self, c: &'a mut Context) -> Self::Future {
+        self(c)
+    }
+}
+
+//捕获外部变量（如配置）的handler/中间件：闭包返回BoxFuture，由该函数标注参数与返回值的生命周期
+//let prefix = Arc::new("api".to_string());
+//engin.hooks(from_fn(move |c| {
+//    let prefix = prefix.clone();
+//    Box::pin(async move {
+//        c.set_header("x-prefix", &prefix);
+//        c.next().await;
+//    })
+//}))
+pub fn from_fn<F>(f: F) -> F
+where
+    F: for<'a> Fn(&'a mut Context) -> BoxFuture<'a, ()> + Send + Sync + 'static,
+{
+    f
+}
+
+//把用户handler包装成统一的Handler
+pub(crate) fn into_handler<H>(handler: H) -> Arc<Handler>
+where
+    H: for<'a> HandlerFn<'a>,
+{
+    let handler = Arc::new(handler);
+    Arc::new(move |c: &mut Context| -> BoxFuture<'_, ()> {
+        let handler = handler.clone();
+        Box::pin(async move {
+            let resp = handler.call(c).await.into_response();
+            write_response(c, resp);
+        })
+    })
+}
+
+//路由handler：1）async fn(c: &mut Context) 2）参数全部实现FromContext的async fn，如：
+//async fn get_user(Path(id): Path<u64>) -> Json<User>
+//M仅用于区分不同形式的handler，使用时由编译器推导
+pub trait IntoHandler<M>: Send + Sync + 'static {
+    fn into_handler(self) -> Arc<Handler>;
+}
+
+#[doc(hidden)]
+pub struct WithContext;
+
+#[doc(hidden)]
+pub struct WithExtractors;
+
+impl<H> IntoHandler<WithContext> for H
+where
+    H: for<'a> HandlerFn<'a>,
+{
+    fn into_handler(self) -> Arc<Handler> {
+        into_handler(self)
+    }
+}
+
+macro_rules! impl_into_handler {
+    ($($ty:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<F, Fut, R, $($ty,)*> IntoHandler<(WithExtractors, $($ty,)*)> for F
+        where
+            F: Fn($($ty,)*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = R> + Send + 'static,
+            R: IntoResponse,
+            $($ty: FromContext + 'static,)*
+        {
+            fn into_handler(self) -> Arc<Handler> {
+                let handler = Arc::new(self);
+                Arc::new(move |c: &mut Context| -> BoxFuture<'_, ()> {
+                    let handler = handler.clone();
+                    Box::pin(async move {
+                        $(
+                            let $ty = match $ty::from_context(c).await {
+                                Ok(value) => value,
+                                Err(e) => return write_response(c, e.into_response()),
+                            };
+                        )*
+                        let resp = handler($($ty,)*).await.into_response();
+                        write_response(c, resp);
+                    })
+                })
+            }
+        }
+    };
+}
+
+impl_into_handler!();
+impl_into_handler!(T1);
+impl_into_handler!(T1, T2);
+impl_into_handler!(T1, T2, T3);
+impl_into_handler!(T1, T2, T3, T4);
+impl_into_handler!(T1, T2, T3, T4, T5);
+impl_into_handler!(T1, T2, T3, T4, T5, T6);
+impl_into_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_into_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
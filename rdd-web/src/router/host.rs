@@ -0,0 +1,112 @@
+use super::trie::{Params, MAX_PARAMS};
+
+/*
+ *按Host路由：按'.'分割为label逐个匹配，忽略大小写
+ *1）精确匹配：admin.example.com
+ *2）参数匹配':'：:tenant.example.com，则能匹配：a.example.com，tenant为a
+ */
+pub(crate) struct Host {
+    pattern: String,
+    param_names: Vec<String>,
+}
+
+impl Host {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.to_ascii_lowercase();
+        let mut param_names = Vec::new();
+        let invalid = || format!("Host不合法:{}", pattern);
+        for label in pattern.split('.') {
+            let name = label.strip_prefix(':');
+            //不能包含路径、端口
+            if label.is_empty() || label.contains('/') || name.unwrap_or(label).contains(':') {
+                return Err(invalid());
+            }
+            match name {
+                Some("") => return Err(invalid()),
+                Some(name) => param_names.push(name.to_string()),
+                None => {}
+            }
+        }
+        if param_names.len() > MAX_PARAMS {
+            return Err(invalid());
+        }
+        Ok(Self {
+            pattern,
+            param_names,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn param_names(&self) -> &[String] {
+        &self.param_names
+    }
+
+    pub fn is_wild(&self) -> bool {
+        !self.param_names.is_empty()
+    }
+
+    //请求的host（已去掉端口并转为小写）是否匹配，参数值写入params
+    pub fn is_match<'h>(&self, host: &'h str, params: &mut Params<'h>) -> bool {
+        let mut labels = host.split('.');
+        for label in self.pattern.split('.') {
+            let value = match labels.next() {
+                Some(value) if !value.is_empty() => value,
+                _ => return false,
+            };
+            if label.starts_with(':') {
+                params.push(value);
+            } else if label != value {
+                return false;
+            }
+        }
+        labels.next().is_none()
+    }
+}
+
+//请求中的host：去掉端口与末尾的'.'，转为小写，如：Admin.Example.com:8080 => admin.example.com
+pub(crate) fn normalize_host(host: &str) -> String {
+    let host = match host.strip_prefix('[') {
+        //ipv6：[::1]:8080
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_match() {
+        let host = Host::new(":tenant.Example.com").unwrap();
+        assert!(host.is_wild());
+        let mut params = Params::new();
+        assert!(host.is_match("acme.example.com", &mut params));
+        assert_eq!(params.values(), ["acme"]);
+        let mut params = Params::new();
+        assert!(!host.is_match("example.com", &mut params));
+        assert!(!host.is_match("a.b.example.com", &mut params));
+        assert!(!host.is_match("acme.example.org", &mut params));
+
+        let host = Host::new("admin.example.com").unwrap();
+        assert!(!host.is_wild());
+        assert!(host.is_match("admin.example.com", &mut Params::new()));
+        assert!(Host::new("admin..com").is_err());
+        assert!(Host::new("example.com/admin").is_err());
+        assert!(Host::new(":.example.com").is_err());
+    }
+
+    #[test]
+    fn test_normalize_host() {
+        assert_eq!(
+            normalize_host("Admin.Example.com:8080"),
+            "admin.example.com"
+        );
+        assert_eq!(normalize_host("example.com."), "example.com");
+        assert_eq!(normalize_host("[::1]:8080"), "::1");
+    }
+}
@@ -2,10 +2,16 @@ pub(crate) mod trie;
 pub(crate) use trie::Node;
 
 pub(crate) mod handler;
+pub use handler::{from_fn, BoxFuture, HandlerFn, IntoHandler};
 
+#[allow(clippy::module_inception)]
 pub(crate) mod router;
+pub use router::{RouteError, RouteInfo, UrlForError};
 
 mod router_group;
+pub(crate) use router_group::GroupId;
 pub use router_group::RouterGroup;
 
-pub(crate) mod utils;
\ No newline at end of file
+pub(crate) mod host;
+pub(crate) mod param;
+pub(crate) mod utils;
@@ -0,0 +1,160 @@
+use regex::Regex;
+
+use super::utils;
+
+/*
+ *路由片段中的参数：[前缀]:名称[<约束>]
+ *1）:id          任意值
+ *2）:id<u64>     内置类型约束：能解析为对应类型
+ *3）:name<[a-z]+> 正则约束：整个片段需完全匹配
+ *4）v:version<\d+> 带静态前缀的参数，如：/v1/items中version为1
+ */
+pub(crate) struct Param<'a> {
+    pub prefix: &'a str,
+    pub name: &'a str,
+    pub constraint: Option<&'a str>,
+}
+
+//解析路由片段中的参数，不是参数片段时返回None；格式不合法时返回原因
+pub(crate) fn parse_param(part: &str) -> Option<Result<Param<'_>, String>> {
+    if part.starts_with('*') {
+        return None;
+    }
+    let (prefix, rest) = part.split_once(':')?;
+    let (name, constraint) = match rest.split_once('<') {
+        Some((name, constraint)) => match constraint.strip_suffix('>') {
+            Some(constraint) if !constraint.is_empty() => (name, Some(constraint)),
+            _ => return Some(Err(format!("invalid constraint in `{}`", part))),
+        },
+        None => (rest, None),
+    };
+    if name.is_empty() {
+        return Some(Err(format!("missing param name in `{}`", part)));
+    }
+    Some(Ok(Param {
+        prefix,
+        name,
+        constraint,
+    }))
+}
+
+//参数约束：内置类型或正则
+pub(crate) enum Constraint {
+    Type(fn(&str) -> bool),
+    Regex(Regex),
+}
+
+macro_rules! parses {
+    ($ty:ty) => {
+        |value: &str| value.parse::<$ty>().is_ok()
+    };
+}
+
+impl Constraint {
+    //内置类型名之外的约束按正则编译，并要求整个片段匹配
+    pub fn new(constraint: &str) -> Result<Self, String> {
+        let check: fn(&str) -> bool = match constraint {
+            "u8" => parses!(u8),
+            "u16" => parses!(u16),
+            "u32" => parses!(u32),
+            "u64" => parses!(u64),
+            "u128" => parses!(u128),
+            "usize" => parses!(usize),
+            "i8" => parses!(i8),
+            "i16" => parses!(i16),
+            "i32" => parses!(i32),
+            "i64" => parses!(i64),
+            "i128" => parses!(i128),
+            "isize" => parses!(isize),
+            "f32" => parses!(f32),
+            "f64" => parses!(f64),
+            "bool" => parses!(bool),
+            _ => {
+                return Regex::new(&format!("^(?:{})$", constraint))
+                    .map(Constraint::Regex)
+                    .map_err(|e| format!("invalid constraint `{}`: {}", constraint, e))
+            }
+        };
+        Ok(Constraint::Type(check))
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            Constraint::Type(check) => check(value),
+            Constraint::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+//参数节点的匹配器：值（解码后）满足约束
+//参数的静态前缀在前缀树中是单独的静态节点，因此这里只匹配去掉前缀后的值
+pub(crate) struct Matcher {
+    constraint: Option<Constraint>,
+}
+
+impl Matcher {
+    pub fn new(param: &Param) -> Result<Self, String> {
+        let constraint = param.constraint.map(Constraint::new).transpose()?;
+        Ok(Self { constraint })
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        if value.is_empty() {
+            return false;
+        }
+        match &self.constraint {
+            Some(constraint) if value.contains('%') => {
+                constraint.is_match(&utils::decode_param(value))
+            }
+            Some(constraint) => constraint.is_match(value),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(part: &str) -> Matcher {
+        Matcher::new(&parse_param(part).unwrap().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_param() {
+        let param = parse_param("v:version<\\d+>").unwrap().unwrap();
+        assert_eq!(param.prefix, "v");
+        assert_eq!(param.name, "version");
+        assert_eq!(param.constraint, Some("\\d+"));
+        let param = parse_param(":id").unwrap().unwrap();
+        assert_eq!(
+            (param.prefix, param.name, param.constraint),
+            ("", "id", None)
+        );
+        assert!(parse_param("list").is_none());
+        assert!(parse_param("*file").is_none());
+        assert!(parse_param(":id<u64").unwrap().is_err());
+        assert!(parse_param(":<u64>").unwrap().is_err());
+        assert!(parse_param(":id<>").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_matcher() {
+        let id = matcher(":id<u64>");
+        assert!(id.is_match("42"));
+        assert!(!id.is_match("abc"));
+        assert!(!id.is_match("-1"));
+        let name = matcher(":name<[a-z0-9_-]+>");
+        assert!(name.is_match("a-b_1"));
+        assert!(!name.is_match("A"));
+        //正则需匹配整个片段
+        assert!(!name.is_match("ab.txt"));
+        let version = matcher(":version<\\d+>");
+        assert!(version.is_match("2"));
+        assert!(!version.is_match(""));
+        assert!(!version.is_match("x"));
+        //约束检查解码后的值
+        assert!(matcher(":name<\\w+ \\w+>").is_match("a%20b"));
+        assert!(Matcher::new(&parse_param(":id<[a->").unwrap().unwrap()).is_err());
+    }
+}
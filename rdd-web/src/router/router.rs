@@ -1,81 +1,321 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    sync::Arc,
+};
 
+use hyper::Method;
 use log::trace;
 
-use super::{handler::Handler, utils, Node};
-use crate::Context;
+use super::{
+    handler::{into_handler, Handler, HandlerFn, IntoHandler},
+    host::Host,
+    trie::{InsertError, Match, Params, Route},
+    utils, GroupId, Node,
+};
+
+//any注册的请求方法
+pub(crate) const ANY_METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "HEAD", "OPTIONS", "DELETE", "CONNECT", "TRACE",
+];
 
 //路由注册表
 pub(crate) struct Router {
     //按照请求方法不同而分类的前缀树：GET前缀树,POST前缀树...
     pub node_tree: HashMap<String, Node>,
-    //路由handler
-    pub handlers: HashMap<String, Box<Handler>>,
+    //命名路由：名称 => 路由，如：user.show => /user/:id
+    pub names: HashMap<String, String>,
+    //最近注册的路由（请求方法，路由），.name()为其命名
+    last_route: Option<(Vec<String>, String)>,
+    //按Host路由：每个host一个Router（同样按请求方法分类），精确host在前，带参数的host在后
+    hosts: Vec<(Host, Router)>,
 }
 
 impl Router {
     pub fn new() -> Self {
         Self {
             node_tree: HashMap::new(),
-            handlers: HashMap::new(),
+            names: HashMap::new(),
+            last_route: None,
+            hosts: Vec::new(),
         }
     }
 }
 
 impl Router {
-    pub(crate) fn add_route<H>(
+    pub(crate) fn add_route<H, M>(
         &mut self,
         method: &str,
         pattern: &str,
-        group_id: Option<&str>,
+        group_id: Option<&GroupId>,
+        handler: H,
+    ) where
+        H: IntoHandler<M>,
+    {
+        self.add_methods(&[method], pattern, group_id, handler);
+    }
+
+    //同一handler注册到多个请求方法，如：GET、POST或PROPFIND等扩展方法（区分大小写）
+    pub(crate) fn add_methods<H, M>(
+        &mut self,
+        methods: &[&str],
+        pattern: &str,
+        group_id: Option<&GroupId>,
         handler: H,
     ) where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
-        if ! pattern.starts_with('/'){
-            panic!("路径必须以'/'开头:{}",pattern)
+        let handler = handler.into_handler();
+        for method in methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                panic!("请求方法不合法:{}", method)
+            }
+            if let Err(e) = self.add_handler(method, pattern, group_id, handler.clone()) {
+                panic!("{}", e)
+            }
         }
-        if self.node_tree.get_mut(method).is_none() {
-            let node = Node::new();
-            self.node_tree.insert(method.to_string(), node);
+        let methods = methods.iter().map(|method| method.to_string()).collect();
+        self.last_route = Some((methods, pattern.to_string()));
+    }
+
+    //路由重复或存在歧义时返回RouteError
+    pub(crate) fn add_handler(
+        &mut self,
+        method: &str,
+        pattern: &str,
+        group_id: Option<&GroupId>,
+        handler: Arc<Handler>,
+    ) -> Result<(), RouteError> {
+        if !pattern.starts_with('/') {
+            panic!("路径必须以'/'开头:{}", pattern)
         }
-        if let Some(node) = self.node_tree.get_mut(method) {
-            let parts = utils::parse_pattern(pattern);
-            node.insert(pattern, parts, group_id, 0);
-            let key = format!("{}_{}", method, pattern);
-            self.handlers.insert(key, Box::new(handler));
+        self.insert_route(method, Route::new(pattern, group_id, handler))?;
+        Ok(())
+    }
+
+    fn insert_route(&mut self, method: &str, route: Route) -> Result<(), RouteError> {
+        let pattern = route.pattern.clone();
+        let node = self
+            .node_tree
+            .entry(method.to_string())
+            .or_insert_with(Node::new);
+        node.insert(&pattern, route).map_err(|e| match e {
+            InsertError::Conflict(existing) => RouteError::Conflict {
+                method: method.to_string(),
+                pattern,
+                existing,
+            },
+            InsertError::Invalid(reason) => RouteError::InvalidPattern { pattern, reason },
+        })
+    }
+
+    //合并其他Router的路由与路由名称：路由加上前缀，分组由nest_group换成挂载后的分组（不属于分组的路由为None）
+    pub(crate) fn mount(
+        &mut self,
+        prefix: &str,
+        other: Router,
+        nest_group: &mut dyn FnMut(Option<&GroupId>) -> GroupId,
+    ) {
+        let mut node_tree: Vec<_> = other.node_tree.into_iter().collect();
+        node_tree.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        for (method, root) in node_tree {
+            for route in root.into_routes() {
+                let group_id = nest_group(route.group_id.as_ref());
+                if let Err(e) = self.insert_route(&method, route.nest(prefix, group_id)) {
+                    panic!("{}", e)
+                }
+            }
+        }
+        for (host, router) in other.hosts {
+            self.router_mut(Some(host.pattern()))
+                .mount(prefix, router, nest_group);
         }
+        for (name, pattern) in other.names {
+            self.add_name(&name, format!("{}{}", prefix, pattern));
+        }
+    }
+
+    //host对应的Router（不存在时创建），None为不区分host的路由
+    pub(crate) fn router_mut(&mut self, host: Option<&str>) -> &mut Router {
+        let host = match host {
+            Some(host) => Host::new(host).unwrap_or_else(|e| panic!("{}", e)),
+            None => return self,
+        };
+        let index = match self
+            .hosts
+            .iter()
+            .position(|(h, _)| h.pattern() == host.pattern())
+        {
+            Some(index) => index,
+            None => {
+                let index = if host.is_wild() {
+                    self.hosts.len()
+                } else {
+                    self.hosts
+                        .iter()
+                        .position(|(h, _)| h.is_wild())
+                        .unwrap_or(self.hosts.len())
+                };
+                self.hosts.insert(index, (host, Router::new()));
+                index
+            }
+        };
+        &mut self.hosts[index].1
     }
 
-    //根据请示路径找到路由节点以及提取路径上的参数：如果节点信息为：/:lang/doc，用户待匹配路径为/c/doc
-    //提输出（节点，（lang,c））
-    pub(crate) fn get_route(
+    //为最近注册的路由命名：名称保存在该路由（host与请求方法）的叶子节点上，url_for不区分host
+    pub(crate) fn name_last_route(&mut self, host: Option<&str>, name: &str) {
+        let router = self.router_mut(host);
+        let (methods, pattern) = match router.last_route.clone() {
+            Some(last) => last,
+            None => panic!("路由命名前需要先注册路由:{}", name),
+        };
+        for method in &methods {
+            if let Some(route) = router
+                .node_tree
+                .get_mut(method)
+                .and_then(|root| root.route_mut(&pattern))
+            {
+                route.name = Some(name.to_string());
+            }
+        }
+        self.add_name(name, pattern);
+    }
+
+    fn add_name(&mut self, name: &str, pattern: String) {
+        if let Some(existing) = self.names.get(name) {
+            if existing != &pattern {
+                panic!(
+                    "{}",
+                    RouteError::DuplicateName {
+                        name: name.to_string(),
+                        pattern,
+                        existing: existing.clone(),
+                    }
+                )
+            }
+        }
+        self.names.insert(name.to_string(), pattern);
+    }
+
+    //根据路由名称生成url：未在路由中使用的参数作为查询参数
+    pub(crate) fn url_for(
         &self,
+        name: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, UrlForError> {
+        let pattern = self
+            .names
+            .get(name)
+            .ok_or_else(|| UrlForError::UnknownRoute {
+                name: name.to_string(),
+            })?;
+        utils::build_url(pattern, params).map_err(|param| UrlForError::MissingParam {
+            name: name.to_string(),
+            param,
+        })
+    }
+
+    //根据请示路径找到路由以及提取路径上的参数：如果路由为：/:lang/doc，用户待匹配路径为/c/doc
+    //提输出（路由，（lang,c））
+    //host（已去掉端口并转为小写）匹配的Router优先，其次为不区分host的路由
+    pub(crate) fn get_route<'r, 'p>(
+        &'r self,
         method: &str,
+        host: Option<&'p str>,
+        path: &'p str,
+    ) -> Option<Match<'r, 'p>> {
+        trace!("待查找的路由：{}", path);
+        if let Some(host) = host {
+            for (h, router) in &self.hosts {
+                let mut params = Params::new();
+                if !h.is_match(host, &mut params) {
+                    continue;
+                }
+                if let Some(m) = router.get_route(method, None, path) {
+                    return Some(m.with_host(h.param_names(), params));
+                }
+            }
+        }
+        self.node_tree.get(method)?.at(path)
+    }
+
+    pub(crate) fn has_hosts(&self) -> bool {
+        !self.hosts.is_empty()
+    }
+
+    //请求host匹配的第一个host路由
+    pub(crate) fn match_host(&self, host: &str) -> Option<&str> {
+        self.hosts
+            .iter()
+            .find(|(h, _)| h.is_match(host, &mut Params::new()))
+            .map(|(h, _)| h.pattern())
+    }
+
+    //路径在哪些请求方法下有路由：用于405响应的Allow头，结果按方法名排序
+    //accept：匹配到的路由是否可用（如严格匹配时要求请求路径为规范形式）
+    pub(crate) fn allowed_methods<'r, F>(
+        &'r self,
+        host: Option<&str>,
         path: &str,
-    ) -> (Option<&Node>, HashMap<String, String>) {
-        trace!("待查找的路由：{}",path);
-        if let Some(root) = self.node_tree.get(method) {
-            let path_parts = utils::parse_pattern(path);
-            return Node::get_node_info_by_root_node(root, &path_parts);
+        accept: F,
+    ) -> Vec<&'r str>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let tree_methods = |router: &'r Router| {
+            router
+                .node_tree
+                .iter()
+                .filter(|(_, root)| root.at(path).is_some_and(|m| accept(&m.route.pattern)))
+                .map(|(method, _)| method.as_str())
+        };
+        let mut methods: Vec<&str> = tree_methods(self).collect();
+        if let Some(host) = host {
+            for (h, router) in &self.hosts {
+                if h.is_match(host, &mut Params::new()) {
+                    methods.extend(tree_methods(router));
+                }
+            }
         }
-        (None, HashMap::new())
+        methods.sort_unstable();
+        methods.dedup();
+        methods
+    }
+
+    //所有路由：（host，请求方法，路由），按host、路由、请求方法排序
+    pub(crate) fn routes(&self) -> Vec<(Option<&str>, &str, &Route)> {
+        let mut routes: Vec<(Option<&str>, &str, &Route)> = self
+            .node_tree
+            .iter()
+            .flat_map(|(method, root)| {
+                root.routes()
+                    .into_iter()
+                    .map(move |route| (None, method.as_str(), route))
+            })
+            .collect();
+        for (host, router) in &self.hosts {
+            routes.extend(
+                router
+                    .routes()
+                    .into_iter()
+                    .map(|(_, method, route)| (Some(host.pattern()), method, route)),
+            );
+        }
+        routes.sort_unstable_by(|a, b| (a.0, &a.2.pattern, a.1).cmp(&(b.0, &b.2.pattern, b.1)));
+        routes
     }
 
     //给指定节点添加“节点中间件”
     pub(crate) fn add_hooks<H>(&mut self, pattern: &str, method: &str, handler: H)
     where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: for<'a> HandlerFn<'a>,
     {
         if let Some(root) = self.node_tree.get_mut(method) {
-            let parts = utils::parse_pattern(&pattern);
-            if let Some(node) = root.search_mut(&parts, 0) {
-                if node.pattern.is_some() {
-                    //添加中间件
-                    node.middlewares.push(Box::new(handler));
-                } else {
-                    panic!("路径异常不能添加中间件")
-                }
+            match root.route_mut(pattern) {
+                //添加中间件
+                Some(route) => route.middlewares.push(into_handler(handler)),
+                None => panic!("路径异常不能添加中间件"),
             }
         } else {
             panic!("路径异常不能添加中间件")
@@ -86,7 +326,99 @@ impl Debug for Router {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Router")
             .field("node_tree", &self.node_tree)
-            .field("handlers size", &self.handlers.len())
+            .field(
+                "hosts",
+                &self
+                    .hosts
+                    .iter()
+                    .map(|(host, router)| (host.pattern(), router))
+                    .collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
+
+//已注册的路由信息：Engine::routes()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    //按Host路由时的host，如：admin.example.com、:tenant.example.com
+    pub host: Option<String>,
+    pub method: String,
+    //完整路由（含分组前缀）
+    pub pattern: String,
+    //所在分组的前缀
+    pub group: Option<String>,
+    //handler之前执行的中间件个数：全局 + 分组 + 节点中间件
+    pub middlewares: usize,
+    pub name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum RouteError {
+    //路由重复，或同一位置参数名/通配符不同导致匹配存在歧义
+    Conflict {
+        method: String,
+        pattern: String,
+        existing: String,
+    },
+    //路由格式不合法，如：参数约束不是合法正则
+    InvalidPattern {
+        pattern: String,
+        reason: String,
+    },
+    //同一名称用于不同路由
+    DuplicateName {
+        name: String,
+        pattern: String,
+        existing: String,
+    },
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteError::Conflict {
+                method,
+                pattern,
+                existing,
+            } => write!(
+                f,
+                "route `{} {}` conflicts with existing route `{} {}`",
+                method, pattern, method, existing
+            ),
+            RouteError::InvalidPattern { pattern, reason } => {
+                write!(f, "invalid route `{}`: {}", pattern, reason)
+            }
+            RouteError::DuplicateName {
+                name,
+                pattern,
+                existing,
+            } => write!(
+                f,
+                "route name `{}` for `{}` is already used by `{}`",
+                name, pattern, existing
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+#[derive(Debug)]
+pub enum UrlForError {
+    UnknownRoute { name: String },
+    MissingParam { name: String, param: String },
+}
+
+impl Display for UrlForError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UrlForError::UnknownRoute { name } => write!(f, "unknown route name `{}`", name),
+            UrlForError::MissingParam { name, param } => {
+                write!(f, "missing param `{}` for route `{}`", param, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UrlForError {}
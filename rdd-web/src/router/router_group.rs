@@ -1,110 +1,223 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use hyper::Method;
 
-use crate::{router::handler::Handler, Context, Engine};
+use crate::{
+    router::{
+        handler::{into_handler, HandlerFn, IntoHandler},
+        router::ANY_METHODS,
+    },
+    Engine,
+};
+
+//分组标识：按Host路由的host + 分组前缀
+//scope区分挂载进来的Engine：每次nest/merge使用新的scope，不同Engine前缀相同的分组中间件互不影响
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct GroupId {
+    pub scope: usize,
+    pub host: String,
+    pub prefix: String,
+}
+
+//挂载时分配的scope，0为Engine自身注册的分组
+static NEXT_SCOPE: AtomicUsize = AtomicUsize::new(1);
+
+impl GroupId {
+    pub fn new(host: Option<&str>, prefix: &str) -> Self {
+        Self {
+            scope: 0,
+            host: host.unwrap_or_default().to_string(),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    //挂载到前缀下：/user => /api/user
+    pub fn nest(&self, prefix: &str, scope: usize) -> Self {
+        Self {
+            scope,
+            host: self.host.clone(),
+            prefix: format!("{}{}", prefix, self.prefix),
+        }
+    }
+
+    pub fn next_scope() -> usize {
+        NEXT_SCOPE.fetch_add(1, Ordering::Relaxed)
+    }
+}
 
 pub struct RouterGroup<'r> {
     pub(crate) prefix: String,
+    //按Host路由的分组：只匹配该host的请求
+    pub(crate) host: Option<String>,
     engin: &'r mut Engine,
 }
 
 impl<'r> RouterGroup<'r> {
-    pub(crate) fn new(prefix: &str, engin: &'r mut Engine) -> Self {
+    pub(crate) fn new(prefix: &str, host: Option<&str>, engin: &'r mut Engine) -> Self {
         Self {
             prefix: prefix.to_string(),
+            host: host.map(String::from),
             engin,
         }
     }
 
-    fn add_route<H>(&mut self, method: &str, sub_pattern: &str, handler: H)
+    //分组中间件、分组404的key：host+前缀
+    fn group_id(&self) -> GroupId {
+        GroupId::new(self.host.as_deref(), &self.prefix)
+    }
+
+    fn add_route<H, M>(&mut self, method: &str, sub_pattern: &str, handler: H)
+    where
+        H: IntoHandler<M>,
+    {
+        self.add_methods(&[method], sub_pattern, handler);
+    }
+
+    fn add_methods<H, M>(&mut self, methods: &[&str], sub_pattern: &str, handler: H)
     where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         let pattern = format!("{}{}", self.prefix, sub_pattern);
-        self.engin.router.add_route(
-            method,
-            pattern.as_str(),
-            Some(self.prefix.as_str()),
-            handler,
-        );
+        let group_id = self.group_id();
+        self.engin
+            .router
+            .router_mut(self.host.as_deref())
+            .add_methods(methods, pattern.as_str(), Some(&group_id), handler);
     }
 
-    pub fn get<S, H>(mut self, sub_pattern: S, handler: H) -> Self
+    pub fn get<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::GET.as_str(), sub_pattern.as_ref(), handler);
         self
     }
-    pub fn post<S, H>(mut self, sub_pattern: S, handler: H) -> Self
+    pub fn post<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::POST.as_str(), sub_pattern.as_ref(), handler);
         self
     }
-    pub fn put<S, H>(mut self, sub_pattern: S, handler: H) -> Self
+    pub fn put<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::PUT.as_str(), sub_pattern.as_ref(), handler);
         self
     }
 
-    pub fn delete<S, H>(mut self, sub_pattern: S, handler: H) -> Self
+    pub fn delete<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::DELETE.as_str(), sub_pattern.as_ref(), handler);
         self
     }
-    pub fn patch<S, H>(mut self, sub_pattern: S, handler: H) -> Self
+    pub fn patch<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::PATCH.as_str(), sub_pattern.as_ref(), handler);
         self
     }
+    pub fn head<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_route(Method::HEAD.as_str(), sub_pattern.as_ref(), handler);
+        self
+    }
+    pub fn options<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_route(Method::OPTIONS.as_str(), sub_pattern.as_ref(), handler);
+        self
+    }
+    pub fn handle<S, H, M>(mut self, method: &str, sub_pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_methods(&[method], sub_pattern.as_ref(), handler);
+        self
+    }
+    pub fn any<S, H, M>(mut self, sub_pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_methods(&ANY_METHODS, sub_pattern.as_ref(), handler);
+        self
+    }
+    pub fn match_methods<S, H, M>(mut self, methods: &[&str], sub_pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_methods(methods, sub_pattern.as_ref(), handler);
+        self
+    }
+
+    //为最近注册的路由命名
+    pub fn name(self, name: &str) -> Self {
+        self.engin
+            .router
+            .name_last_route(self.host.as_deref(), name);
+        self
+    }
+
+    //分组内的404处理：如/api下返回json格式的404，会经过全局与分组中间件
+    pub fn no_route<H, M>(self, handler: H) -> Self
+    where
+        H: IntoHandler<M>,
+    {
+        self.engin
+            .add_group_no_route(self.group_id(), handler.into_handler());
+        self
+    }
 
     //添加中间件
-    pub fn hooks<H>(mut self, handler: H) -> Self
+    pub fn hooks<H>(self, handler: H) -> Self
     where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: for<'a> HandlerFn<'a>,
     {
-        if let Some(md_vec) = self.engin.groups.get_mut(&self.prefix) {
-            md_vec.push(Box::new(handler));
-        } else {
-            let mut middlewares: Vec<Box<Handler>> = Vec::new();
-            middlewares.push(Box::new(handler));
-            self.engin.groups.insert(self.prefix.clone(), middlewares);
-        }
+        self.engin
+            .groups
+            .entry(self.group_id())
+            .or_default()
+            .push(into_handler(handler));
         self
     }
     //给指定的（路径，方法）添加中间件
     pub fn add_hooks<S, H>(self, sub_pattern: S, method: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: for<'a> HandlerFn<'a>,
     {
         let pattern = format!("{}{}", &self.prefix, sub_pattern.as_ref());
         self.engin
             .router
+            .router_mut(self.host.as_deref())
             .add_hooks(pattern.as_str(), method.as_ref(), handler);
         self
     }
     //路由分组
-    pub fn group<S>(&mut self, prefix: S) -> RouterGroup
+    pub fn group<S>(&mut self, prefix: S) -> RouterGroup<'_>
     where
         S: AsRef<str>,
     {
         let new_prefix = format!("{}{}", &self.prefix, prefix.as_ref());
-        self.engin
-            .groups
-            .insert(prefix.as_ref().to_string(), Vec::new());
-        RouterGroup::new(new_prefix.as_str(), self.engin)
+        let group = RouterGroup::new(new_prefix.as_str(), self.host.as_deref(), self.engin);
+        group.engin.groups.entry(group.group_id()).or_default();
+        group
     }
 }
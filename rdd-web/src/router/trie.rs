@@ -1,385 +1,692 @@
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::{Arc, OnceLock},
+};
 
-use super::{handler::Handler, utils};
+use super::{
+    handler::Handler,
+    param::{self, Matcher},
+    utils, GroupId,
+};
 /*
- *前缀树的简单实现：前缀树是一个简单的支持动态路由：如：/hello/:name，则能匹配：/hello/zs,hello/hmm
+ *压缩前缀树（radix tree）：公共前缀合并为一个节点，如：/user/index与/user/info => /user/in -> [dex, fo]
  *目前前缀树支持：
  *1）静态路由：/user/list,/user/index
  *2) 参数匹配':'：/hello/:name，则能匹配：/hello/zs,hello/hmm
  *3）通配符'*':/static/\*filepath  则能匹配：/static/zzz.js,
- *1、构建前缀树：根据用户的路由路径构建前缀树
+ *4）参数约束：/user/:id<u64>、/v:version<\d+>/items，不满足约束的片段继续尝试其他节点
+ *路由（handler与节点中间件）保存在叶子节点上，查找时不分配内存
+ *路由完整的调用链（全局、分组、节点中间件以及handler）首次匹配时解析并保存在叶子节点上
  */
 
-pub(crate) struct Node {
-    pub pattern: Option<String>,        // 待匹配路由，例如 /p/:lang
-    pub part: Option<String>,           // 路由中的一部分，例如 :lang
-    pub children: Vec<Node>,            // 子节点，例如 [doc, tutorial, intro]
-    pub is_wild: bool,                  // 是否精确匹配，part 含有 : 或 * 时为true
-    pub middlewares: Vec<Box<Handler>>, //节点中间单件
-    pub group_id: Option<String>,       //其实就是分组前缀
+//单个路由最多的参数个数
+pub(crate) const MAX_PARAMS: usize = 16;
+
+//叶子节点上的路由
+pub(crate) struct Route {
+    pub pattern: String,                //注册时的路由，例如 /p/:lang
+    pub group_id: Option<GroupId>,      //所在分组：host + 分组前缀
+    pub handler: Arc<Handler>,          //路由handler
+    pub middlewares: Vec<Arc<Handler>>, //节点中间件
+    param_names: Vec<String>,           //参数名，与匹配到的参数值一一对应
+    pub chain: OnceLock<Chain>,         //完整的调用链，Engine放入Arc后首次匹配时解析
+    pub name: Option<String>,           //路由名称：.name()命名
 }
 
-impl Node {
-    pub fn new() -> Self {
+//调用链：中间件与handler按执行顺序排列，请求之间共享
+pub(crate) type Chain = Arc<[Arc<Handler>]>;
+
+impl Route {
+    pub fn new(pattern: &str, group_id: Option<&GroupId>, handler: Arc<Handler>) -> Self {
         Self {
-            pattern: None,
-            part: None,
-            children: Vec::new(),
-            is_wild: false,
+            pattern: pattern.to_string(),
+            group_id: group_id.cloned(),
+            handler,
             middlewares: Vec::new(),
-            group_id: None,
+            param_names: utils::param_names(pattern).map(String::from).collect(),
+            chain: OnceLock::new(),
+            name: None,
         }
     }
+
+    //挂载到前缀下：/users => /api/users
+    pub fn nest(mut self, prefix: &str, group_id: GroupId) -> Self {
+        self.pattern = format!("{}{}", prefix, self.pattern);
+        self.group_id = Some(group_id);
+        self.param_names = utils::param_names(&self.pattern)
+            .map(String::from)
+            .collect();
+        self.chain = OnceLock::new();
+        self
+    }
 }
 
-impl Node {
-    //在node孩子节点中查找是否有孩子匹配上，该方法用于节点插入
-    pub fn match_child_mut(&mut self, part: &str) -> Option<&mut Node> {
-        for node in self.children.iter_mut() {
-            if node.is_wild {
-                return Some(node);
-            }
-            if let Some(p) = &node.part {
-                if p == part {
-                    return Some(node);
-                }
-            }
+//查找时提取的参数值（未解码），定长数组避免分配
+pub(crate) struct Params<'p> {
+    values: [&'p str; MAX_PARAMS],
+    len: usize,
+}
+
+impl<'p> Params<'p> {
+    pub fn new() -> Self {
+        Self {
+            values: [""; MAX_PARAMS],
+            len: 0,
         }
-        None
     }
 
-    //在node孩子节点中查找是否有孩子匹配上，把所有能匹配上的节点都找出来
-    fn match_children(&self, part: &str) -> Vec<&Node> {
-        let mut children = Vec::new();
-        for node in &self.children {
-            if node.is_wild {
-                children.push(node);
-                continue;
-            }
-            if let Some(p) = &node.part {
-                if p == part {
-                    children.push(node);
-                }
+    pub fn push(&mut self, value: &'p str) {
+        //插入路由时已限制参数个数
+        self.values[self.len] = value;
+        self.len += 1;
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    pub fn values(&self) -> &[&'p str] {
+        &self.values[..self.len]
+    }
+}
+
+//查找结果：路由以及路径上的参数
+pub(crate) struct Match<'r, 'p> {
+    pub route: &'r Route,
+    params: Params<'p>,
+    //按Host路由时host中的参数：（参数名，参数值）
+    host_names: &'r [String],
+    host_params: Params<'p>,
+}
+
+impl<'r, 'p> Match<'r, 'p> {
+    //（参数名，参数值）按路由中出现的顺序
+    pub fn params(&self) -> impl Iterator<Item = (&'r str, &'p str)> + '_ {
+        self.route
+            .param_names
+            .iter()
+            .map(String::as_str)
+            .zip(self.params.values().iter().copied())
+    }
+
+    //host中的参数，如：:tenant.example.com => (tenant,a)
+    pub fn host_params(&self) -> impl Iterator<Item = (&'r str, &'p str)> + '_ {
+        self.host_names
+            .iter()
+            .map(String::as_str)
+            .zip(self.host_params.values().iter().copied())
+    }
+
+    pub fn with_host(mut self, names: &'r [String], params: Params<'p>) -> Self {
+        self.host_names = names;
+        self.host_params = params;
+        self
+    }
+}
+
+//插入路由失败的原因
+#[derive(Debug, PartialEq)]
+pub(crate) enum InsertError {
+    //与已存在的路由冲突
+    Conflict(String),
+    //路由片段不合法，如：参数约束不是合法正则
+    Invalid(String),
+}
+
+enum Kind {
+    Static,
+    Param(Matcher),
+    CatchAll,
+}
+
+pub(crate) struct Node {
+    path: String, // 静态节点为公共前缀，例如 /user/；参数节点为 :id<u64>；通配符节点为 *file
+    kind: Kind,
+    children: Vec<Node>, // 静态子节点在前（首字符互不相同），其后为参数、通配符子节点（按匹配优先级排列）
+    indices: Vec<char>,  // 静态子节点的首字符，与children前部一一对应
+    pub route: Option<Route>,
+}
+
+//路由按静态文本、参数、通配符切分：/v:version<\d+>/items => [/v, :version<\d+>, /items]
+enum Piece<'a> {
+    Static(String),
+    Param(&'a str),
+    CatchAll(&'a str),
+}
+
+fn pieces(pattern: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    for part in utils::parse_pattern(pattern) {
+        text.push('/');
+        if part.starts_with('*') {
+            pieces.push(Piece::Static(std::mem::take(&mut text)));
+            pieces.push(Piece::CatchAll(part));
+            continue;
+        }
+        match param::parse_param(part) {
+            Some(param) => {
+                let prefix = param?.prefix;
+                text.push_str(prefix);
+                pieces.push(Piece::Static(std::mem::take(&mut text)));
+                pieces.push(Piece::Param(&part[prefix.len()..]));
             }
+            None => text.push_str(part),
         }
-        return children;
     }
+    if pieces.is_empty() && text.is_empty() {
+        //根路由：/
+        text.push('/');
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Static(text));
+    }
+    Ok(pieces)
+}
 
-    //在node孩子节点中查找是否有孩子匹配上，把所有能匹配上的节点都找出来
-    fn match_children_mut(&mut self, part: &str) -> Vec<&mut Node> {
-        let mut children = Vec::new();
-        for node in self.children.iter_mut() {
-            if node.is_wild {
-                children.push(node);
-                continue;
-            }
-            if let Some(p) = &node.part {
-                if p == part {
-                    children.push(node);
+impl Node {
+    pub fn new() -> Self {
+        Node::with_kind(String::new(), Kind::Static)
+    }
+
+    fn with_kind(path: String, kind: Kind) -> Self {
+        Self {
+            path,
+            kind,
+            children: Vec::new(),
+            indices: Vec::new(),
+            route: None,
+        }
+    }
+
+    //构建前缀树：路由重复或同一位置出现有歧义的参数/通配符时返回已存在的冲突路由
+    pub fn insert(&mut self, pattern: &str, route: Route) -> Result<(), InsertError> {
+        let pieces = pieces(pattern).map_err(InsertError::Invalid)?;
+        if route.param_names.len() > MAX_PARAMS {
+            let reason = format!("more than {} params", MAX_PARAMS);
+            return Err(InsertError::Invalid(reason));
+        }
+        self.insert_pieces(&pieces, route)
+    }
+
+    //当前节点已完全匹配，继续插入剩余部分
+    fn insert_pieces(&mut self, pieces: &[Piece], route: Route) -> Result<(), InsertError> {
+        let (piece, rest) = match pieces.split_first() {
+            Some(first) => first,
+            None => {
+                if let Some(existing) = &self.route {
+                    return Err(InsertError::Conflict(existing.pattern.clone()));
                 }
+                self.route = Some(route);
+                return Ok(());
             }
+        };
+        let part = match piece {
+            Piece::Static(text) => return self.insert_static(text, rest, route),
+            Piece::Param(part) | Piece::CatchAll(part) => *part,
+        };
+        //同一位置只能有一个通配符：/static/*file与/static/*path冲突
+        //约束相同的参数只能有一个：/user/:id与/user/:name冲突，/user/:id<u64>与/user/:name可共存
+        let statics = self.indices.len();
+        let wild = &self.children[statics..];
+        if let Some(child) = wild
+            .iter()
+            .find(|child| child.path != part && param_key(&child.path) == param_key(part))
+        {
+            let existing = child.any_pattern().unwrap_or_default().to_string();
+            return Err(InsertError::Conflict(existing));
         }
-        return children;
+        let index = match wild.iter().position(|child| child.path == part) {
+            Some(index) => statics + index,
+            None => {
+                let kind = match piece {
+                    Piece::CatchAll(_) => Kind::CatchAll,
+                    _ => match param::parse_param(part) {
+                        Some(Ok(param)) => {
+                            Kind::Param(Matcher::new(&param).map_err(InsertError::Invalid)?)
+                        }
+                        _ => return Err(InsertError::Invalid(format!("invalid param `{}`", part))),
+                    },
+                };
+                let child = Node::with_kind(part.to_string(), kind);
+                //参数子节点按匹配优先级排序：带约束的参数 > :参数 > *通配符，与注册顺序无关
+                let index = wild
+                    .iter()
+                    .position(|node| node.priority() > child.priority())
+                    .map_or(self.children.len(), |index| statics + index);
+                self.children.insert(index, child);
+                index
+            }
+        };
+        self.children[index].insert_pieces(rest, route)
     }
 
-    //构建前缀树
-    pub fn insert(
+    //插入静态文本：与首字符相同的子节点共享公共前缀，必要时拆分子节点
+    fn insert_static(
         &mut self,
-        pattern: &str,
-        parts: Vec<&str>,
-        group_id: Option<&str>,
-        height: usize,
-    ) {
-        if parts.len() == height {
-            self.pattern = Some(pattern.to_string());
-            return;
+        text: &str,
+        rest: &[Piece],
+        route: Route,
+    ) -> Result<(), InsertError> {
+        let first = match text.chars().next() {
+            Some(first) => first,
+            None => return self.insert_pieces(rest, route),
+        };
+        if let Some(index) = self.indices.iter().position(|&c| c == first) {
+            let child = &mut self.children[index];
+            let common = common_prefix(&child.path, text);
+            if common < child.path.len() {
+                child.split(common);
+            }
+            return child.insert_static(&text[common..], rest, route);
         }
-        let part = parts.get(height);
-        if let Some(&part) = part {
-            let child = self.match_child_mut(part);
-            if let Some(child) = child {
-                // 如果节点已经存在，递归深度遍历
-                child.insert(pattern, parts, group_id, height + 1);
-            } else {
-                //节点不存在，新增节点
-                let mut child = Node::new();
-                child.part = Some(part.to_string());
-                child.is_wild = Node::is_wild(part);
-                //如果路由节点在路由分组中，则登记所在的分组
-                if let Some(group_id) = group_id {
-                    child.group_id = Some(group_id.to_string());
+        let index = self.indices.len();
+        self.indices.push(first);
+        self.children
+            .insert(index, Node::with_kind(text.to_string(), Kind::Static));
+        self.children[index].insert_pieces(rest, route)
+    }
+
+    //静态节点在at处拆分：/user/index => /user/in -> [dex]
+    fn split(&mut self, at: usize) {
+        let suffix = self.path.split_off(at);
+        let mut child = Node::with_kind(suffix, Kind::Static);
+        child.children = std::mem::take(&mut self.children);
+        child.indices = std::mem::take(&mut self.indices);
+        child.route = self.route.take();
+        self.indices = child.path.chars().next().into_iter().collect();
+        self.children = vec![child];
+    }
+
+    //树遍历：根据清理后的请求路径查找路由，静态 > 参数 > 通配符，匹配失败时回溯
+    pub fn at<'r, 'p>(&'r self, path: &'p str) -> Option<Match<'r, 'p>> {
+        let mut params = Params::new();
+        let route = self.lookup(path, &mut params)?;
+        Some(Match {
+            route,
+            params,
+            host_names: &[],
+            host_params: Params::new(),
+        })
+    }
+
+    fn lookup<'r, 'p>(&'r self, path: &'p str, params: &mut Params<'p>) -> Option<&'r Route> {
+        let rest = match &self.kind {
+            Kind::Static => path.strip_prefix(self.path.as_str())?,
+            Kind::Param(matcher) => {
+                //参数匹配到下一个'/'为止
+                let end = path.find('/').unwrap_or(path.len());
+                let (value, rest) = path.split_at(end);
+                if !matcher.is_match(value) || !utils::is_safe_param(value) {
+                    return None;
                 }
-                let len = self.children.len();
-                self.children.insert(len, child);
-                //？？重新取出继续深度遍历，新增节点
-                let child = self.children.get_mut(len);
-                if let Some(child) = child {
-                    child.insert(pattern, parts, group_id, height + 1);
+                params.push(value);
+                rest
+            }
+            Kind::CatchAll => {
+                if path.is_empty() || !utils::is_safe_param(path) {
+                    return None;
                 }
+                params.push(path);
+                return self.route.as_ref();
             }
+        };
+        if rest.is_empty() {
+            return self.route.as_ref();
         }
-    }
-
-    //树遍历：根据真实路径片段（url片段）查询路中是否有匹配的节点
-    pub fn search(&self, parts: &Vec<&str>, height: usize) -> Option<&Node> {
-        if let Some(part) = &self.part {
-            // "*"前缀要特殊处理
-            if parts.len() == height || part.starts_with('*') {
-                //只有pattern有值的才是合法节点
-                let node = match self.pattern {
-                    Some(_) => Some(self),
-                    None => None,
-                };
-                return node;
+        let len = params.len;
+        let first = rest.chars().next();
+        if let Some(index) = self.indices.iter().position(|&c| Some(c) == first) {
+            if let Some(route) = self.children[index].lookup(rest, params) {
+                return Some(route);
             }
+            params.truncate(len);
         }
-        if let Some(&part) = parts.get(height) {
-            //在孩子节点中查找节点
-            let children = self.match_children(part);
-            for child in children {
-                let c = child.search(parts, height + 1);
-                if let Some(_) = c {
-                    return c;
-                }
+        for child in &self.children[self.indices.len()..] {
+            if let Some(route) = child.lookup(rest, params) {
+                return Some(route);
             }
+            params.truncate(len);
         }
         None
     }
 
-    //根据输入的节点路径，查找节点，该方法现在只要添加中单件时使用（算法要优化）添加节点中间件时用
-    pub fn search_mut(&mut self, parts: &Vec<&str>, height: usize) -> Option<&mut Node> {
-        if let Some(part) = &self.part {
-            if parts.len() == height || part.starts_with('*') {
-                let node = match self.pattern {
-                    Some(_) => Some(self),
-                    None => None,
-                };
-                return node;
+    //根据注册时的路由查找（与注册时的写法一致，忽略多余的'/'），添加节点中间件时用
+    pub fn route_mut(&mut self, pattern: &str) -> Option<&mut Route> {
+        self.find_route_mut(&normalize(pattern))
+    }
+
+    fn find_route_mut(&mut self, key: &str) -> Option<&mut Route> {
+        if let Some(route) = &mut self.route {
+            if normalize(&route.pattern) == key {
+                return Some(route);
             }
         }
-        if let Some(&part) = parts.get(height) {
-            let children = self.match_children_mut(part);
-            for child in children {
-                let c = child.search_mut(parts, height + 1);
-                if let Some(_) = c {
-                    return c;
-                }
-            }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_route_mut(key))
+    }
+
+    //取出子树中所有的路由
+    pub fn into_routes(self) -> Vec<Route> {
+        let mut routes: Vec<Route> = self.route.into_iter().collect();
+        for child in self.children {
+            routes.extend(child.into_routes());
         }
-        None
+        routes
     }
-    //路由节点提取以及，路由参数提取：代码可放在router中，呵呵
-    pub fn get_node_info_by_root_node<'a>(
-        root: &'a Node,
-        path_parts: &Vec<&str>,
-    ) -> (Option<&'a Node>, HashMap<String, String>) {
-        let mut params = HashMap::new();
-        let node = root.search(&path_parts, 0);
-        if let Some(node) = node {
-            if let Some(pattern) = &node.pattern {
-                let parts = utils::parse_pattern(pattern);
-                for (index, &part) in parts.iter().enumerate() {
-                    if part.starts_with(':') {
-                        params.insert(String::from(&part[1..]), String::from(path_parts[index]));
-                    }
-                    if part.starts_with('*') && parts.len() > 1 {
-                        let join_vec = path_parts.get(index..).unwrap().to_vec();
-                        params.insert(String::from(&part[1..]), join_vec.join("/"));
-                    }
-                }
-                return (Some(node), params);
-            }
+
+    //子树中所有的路由
+    pub fn routes(&self) -> Vec<&Route> {
+        let mut routes = Vec::new();
+        self.collect_routes(&mut routes);
+        routes
+    }
+
+    fn collect_routes<'r>(&'r self, routes: &mut Vec<&'r Route>) {
+        routes.extend(&self.route);
+        for child in &self.children {
+            child.collect_routes(routes);
         }
-        (None, params)
     }
 
-    pub fn get_middlewares(&self) -> Vec<&Handler> {
-        let mut middlewares: Vec<&Handler> = Vec::with_capacity(self.middlewares.len());
-        for handler in self.middlewares.iter() {
-            middlewares.push(handler.as_ref());
+    //子树中任意一个已注册的路由：用于冲突提示
+    fn any_pattern(&self) -> Option<&str> {
+        self.route
+            .as_ref()
+            .map(|route| route.pattern.as_str())
+            .or_else(|| self.children.iter().find_map(|child| child.any_pattern()))
+    }
+
+    //匹配优先级：数值越小越优先
+    fn priority(&self) -> u8 {
+        match self.kind {
+            Kind::Static => 0,
+            Kind::Param(_) if self.path.contains('<') => 1,
+            Kind::Param(_) => 2,
+            Kind::CatchAll => 3,
         }
-        middlewares
     }
-    //是否精匹配
-    fn is_wild(part: &str) -> bool {
-        return part.starts_with(':') || part.starts_with('*');
+}
+
+//路由的规范形式：/user//:id/ => /user/:id
+fn normalize(pattern: &str) -> String {
+    format!("/{}", utils::parse_pattern(pattern).join("/"))
+}
+
+//公共前缀的字节长度（按字符比较，保证在字符边界上）
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.chars()
+        .zip(b.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
+//参数去掉名称后的部分，相同时存在歧义：:id<\d+> => (:,\d+)，*file => (*,None)
+fn param_key(part: &str) -> (&str, Option<&str>) {
+    match param::parse_param(part) {
+        Some(Ok(param)) => (":", param.constraint),
+        _ => (part.get(..1).unwrap_or_default(), None),
     }
 }
 
 impl Debug for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Node")
-            .field("pattern", &self.pattern)
-            .field("part", &self.part)
+            .field("path", &self.path)
+            .field(
+                "pattern",
+                &self.route.as_ref().map(|route| route.pattern.as_str()),
+            )
             .field("children", &self.children)
-            .field("is_wild", &self.is_wild)
             .finish()
     }
 }
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.part.eq(&other.part)
-            && self.pattern.eq(&other.pattern)
-            && self.is_wild.eq(&other.is_wild)
-            && self.children.eq(&other.children)
-    }
-}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use utils;
+    use crate::{router::handler::into_handler, Context};
 
-    #[test]
-    fn test_tree_node_build1() {
+    fn route(pattern: &str) -> Route {
+        Route::new(pattern, None, into_handler(async |_c: &mut Context| {}))
+    }
+
+    fn build_tree(patterns: &[&str]) -> Node {
         let mut root = Node::new();
-        let pattern = "/user/index";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let target = get_target_tree1();
-
-        debug_assert_eq!(&target, &root);
-
-        fn get_target_tree1() -> Node {
-            let mut root = Node::new();
-            let mut child1 = Node::new();
-            child1.part = Some("user".to_string());
-            let mut child11 = Node::new();
-            child11.part = Some("index".to_string());
-            child11.pattern = Some("/user/index".to_string());
-            child1.children.push(child11);
-            root.children.push(child1);
-            root
+        for &pattern in patterns {
+            root.insert(pattern, route(pattern)).unwrap();
+        }
+        root
+    }
+
+    //树结构：每行一个节点，缩进表示层级，[]中为节点上的路由
+    fn dump(node: &Node, depth: usize, out: &mut String) {
+        out.push_str(&format!("{}{}", "  ".repeat(depth), node.path));
+        if let Some(route) = &node.route {
+            out.push_str(&format!(" [{}]", route.pattern));
+        }
+        out.push('\n');
+        for child in &node.children {
+            dump(child, depth + 1, out);
         }
     }
 
+    fn tree(root: &Node) -> String {
+        let mut out = String::new();
+        for child in &root.children {
+            dump(child, 0, &mut out);
+        }
+        out
+    }
+
+    //未匹配时返回空字符串
+    fn search_pattern(root: &Node, path: &str) -> String {
+        root.at(path)
+            .map(|m| m.route.pattern.clone())
+            .unwrap_or_default()
+    }
+
+    fn search_params(root: &Node, path: &str) -> Vec<(String, String)> {
+        let m = root.at(path).unwrap();
+        m.params()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_node_build1() {
+        let root = build_tree(&["/user/index"]);
+        assert_eq!(tree(&root), "/user/index [/user/index]\n");
+        //公共前缀拆分为独立节点
+        let root = build_tree(&["/user/index", "/user/info", "/user"]);
+        assert_eq!(
+            tree(&root),
+            "/user [/user]\n  /in\n    dex [/user/index]\n    fo [/user/info]\n"
+        );
+    }
+
     #[test]
     fn test_tree_node_build2() {
-        let mut root = Node::new();
-        let pattern = "/hello/:name";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let target = get_target_tree2();
-
-        debug_assert_eq!(&target, &root);
-
-        fn get_target_tree2() -> Node {
-            let mut root = Node::new();
-            let mut child1 = Node::new();
-            child1.part = Some("hello".to_string());
-            let mut child11 = Node::new();
-            child11.part = Some(":name".to_string());
-            child11.pattern = Some("/hello/:name".to_string());
-            child11.is_wild = true;
-            child1.children.push(child11);
-            root.children.push(child1);
-            root
-        }
+        let root = build_tree(&["/hello/:name"]);
+        assert_eq!(tree(&root), "/hello/\n  :name [/hello/:name]\n");
+        let root = build_tree(&["/v:version<\\d+>/items", "/v1/items"]);
+        assert_eq!(
+            tree(&root),
+            "/v\n  1/items [/v1/items]\n  :version<\\d+>\n    /items [/v:version<\\d+>/items]\n"
+        );
     }
 
     #[test]
     fn test_tree_node_build3() {
-        let mut root = Node::new();
-        let pattern = "/static/*filepath";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let target = get_target_tree3();
-
-        debug_assert_eq!(&target, &root);
-
-        fn get_target_tree3() -> Node {
-            let mut root = Node::new();
-            let mut child1 = Node::new();
-            child1.part = Some("static".to_string());
-            let mut child11 = Node::new();
-            child11.part = Some("*filepath".to_string());
-            child11.pattern = Some("/static/*filepath".to_string());
-            child11.is_wild = true;
-            child1.children.push(child11);
-            root.children.push(child1);
-            root
-        }
+        let root = build_tree(&["/static/*filepath", "/"]);
+        assert_eq!(
+            tree(&root),
+            "/ [/]\n  static/\n    *filepath [/static/*filepath]\n"
+        );
     }
 
     #[test]
     fn test_tree_node_search() {
-        let mut root = Node::new();
-        let pattern = "/user/index";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let path = "/user/index";
-        let path_parts = utils::parse_pattern(path);
-        let info = Node::get_node_info_by_root_node(&root, &path_parts);
-        let mut target = Node::new();
-        target.part = Some("index".to_string());
-        target.pattern = Some("/user/index".to_string());
-        target.is_wild = false;
-        let target_params = HashMap::new();
-
-        //结果assert
-        debug_assert_eq!(Some(&target), info.0);
-        debug_assert_eq!(target_params, info.1);
+        let root = build_tree(&["/", "/user/index", "/user/info", "/user/"]);
+        assert_eq!(search_pattern(&root, "/"), "/");
+        assert_eq!(search_pattern(&root, "/user/index"), "/user/index");
+        assert_eq!(search_pattern(&root, "/user/info"), "/user/info");
+        assert_eq!(search_pattern(&root, "/user"), "/user/");
+        assert_eq!(search_pattern(&root, "/user/in"), "");
+        assert_eq!(search_pattern(&root, "/user/indexes"), "");
     }
+
     #[test]
     fn test_tree_node_search_param_get() {
-        let mut root = Node::new();
-        let pattern = "/hello/:name";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let path = "/hello/zs";
-        let path_parts = utils::parse_pattern(path);
-        let info = Node::get_node_info_by_root_node(&root, &path_parts);
-        let mut target = Node::new();
-        target.part = Some(":name".to_string());
-        target.pattern = Some("/hello/:name".to_string());
-        target.is_wild = true;
-        let mut target_params = HashMap::new();
-        target_params.insert("name".to_string(), "zs".to_string());
-        
-        debug_assert_eq!(Some(&target), info.0);
-        debug_assert_eq!(target_params, info.1);
+        let root = build_tree(&["/hello/:name", "/:lang/doc"]);
+        assert_eq!(search_pattern(&root, "/hello/zs"), "/hello/:name");
+        assert_eq!(
+            search_params(&root, "/hello/zs"),
+            [("name".to_string(), "zs".to_string())]
+        );
+        assert_eq!(
+            search_params(&root, "/c/doc"),
+            [("lang".to_string(), "c".to_string())]
+        );
+        assert_eq!(search_pattern(&root, "/hello/zs/x"), "");
     }
 
     #[test]
     fn test_tree_node_search_param_get2() {
-        let mut root = Node::new();
-        let pattern = "/static/*imagefile";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let path = "/static/image1.jpg";
-        let path_parts = utils::parse_pattern(path);
-        let info = Node::get_node_info_by_root_node(&root, &path_parts);
-        let mut target = Node::new();
-        target.part = Some("*imagefile".to_string());
-        target.pattern = Some("/static/*imagefile".to_string());
-        target.is_wild = true;
-        let mut target_params = HashMap::new();
-        target_params.insert("imagefile".to_string(), "image1.jpg".to_string());
+        let root = build_tree(&["/user/:id/posts/:post"]);
+        assert_eq!(
+            search_params(&root, "/user/7/posts/12"),
+            [
+                ("id".to_string(), "7".to_string()),
+                ("post".to_string(), "12".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree_node_search_param_get3() {
+        let root = build_tree(&["/static/*filepath"]);
+        assert_eq!(
+            search_params(&root, "/static/css/app.css"),
+            [("filepath".to_string(), "css/app.css".to_string())]
+        );
+        assert_eq!(search_pattern(&root, "/static"), "");
+    }
 
+    #[test]
+    fn test_tree_node_search_static_priority() {
+        //不论注册顺序，静态 > :参数 > *通配符
+        let orders = [
+            ["/user/:id", "/user/list", "/user/*rest"],
+            ["/user/*rest", "/user/:id", "/user/list"],
+            ["/user/list", "/user/*rest", "/user/:id"],
+        ];
+        for patterns in orders {
+            let root = build_tree(&patterns);
+            assert_eq!(search_pattern(&root, "/user/list"), "/user/list");
+            assert_eq!(search_pattern(&root, "/user/7"), "/user/:id");
+            assert_eq!(search_pattern(&root, "/user/7/x"), "/user/*rest");
+        }
+    }
 
-        debug_assert_eq!(Some(&target), info.0);
-        debug_assert_eq!(target_params, info.1);
+    #[test]
+    fn test_tree_node_search_backtracking() {
+        let root = build_tree(&["/user/list/all", "/user/:id/posts", "/*path"]);
+        //静态分支/user/list没有posts子节点，回溯到:id分支
+        assert_eq!(search_pattern(&root, "/user/list/posts"), "/user/:id/posts");
+        assert_eq!(search_pattern(&root, "/user/list/all"), "/user/list/all");
+        //都不匹配时回溯到根节点的通配符
+        assert_eq!(search_pattern(&root, "/user/list/x"), "/*path");
+        assert_eq!(
+            search_params(&root, "/user/list/posts"),
+            [("id".to_string(), "list".to_string())]
+        );
+        //回溯时丢弃失败分支上的参数
+        assert_eq!(
+            search_params(&root, "/user/7/x"),
+            [("path".to_string(), "user/7/x".to_string())]
+        );
     }
 
     #[test]
-    fn test_tree_node_search_param_get3() {
-        let mut root = Node::new();
-        let pattern = "/static/*imagefile";
-        let parts = utils::parse_pattern(pattern);
-        root.insert(pattern, parts, None, 0);
-        let path = "/static/user/image1.jpg";
-        let path_parts = utils::parse_pattern(path);
-        let info = Node::get_node_info_by_root_node(&root, &path_parts);
-        let mut target = Node::new();
-        target.part = Some("*imagefile".to_string());
-        target.pattern = Some("/static/*imagefile".to_string());
-        target.is_wild = true;
-        let mut target_params = HashMap::new();
-        target_params.insert("imagefile".to_string(), "user/image1.jpg".to_string());
-
-        
-
-        debug_assert_eq!(Some(&target), info.0);
-        debug_assert_eq!(target_params, info.1);
+    fn test_tree_node_insert_conflict() {
+        let patterns = [
+            "/user/:id",
+            "/user/list",
+            "/user/:id/posts",
+            "/static/*file",
+        ];
+        let mut root = build_tree(&patterns);
+        let conflicts = [
+            ("/user/:name", "/user/:id"),
+            ("/user/:id/", "/user/:id"),
+            ("/user/list", "/user/list"),
+            ("/static/*path", "/static/*file"),
+        ];
+        for (pattern, existing) in conflicts {
+            let err = root.insert(pattern, route(pattern)).unwrap_err();
+            assert_eq!(
+                err,
+                InsertError::Conflict(existing.to_string()),
+                "{}",
+                pattern
+            );
+        }
+        //约束不同的参数可以共存，约束相同而名称不同则冲突
+        let mut root = build_tree(&["/user/:id<u64>", "/user/:name"]);
+        let err = root
+            .insert("/user/:uid<u64>", route("/user/:uid<u64>"))
+            .unwrap_err();
+        assert_eq!(err, InsertError::Conflict("/user/:id<u64>".to_string()));
+        let err = root
+            .insert("/user/:id<[a->", route("/user/:id<[a->"))
+            .unwrap_err();
+        assert!(matches!(err, InsertError::Invalid(_)));
+    }
+
+    #[test]
+    fn test_tree_node_search_constraint() {
+        let root = build_tree(&[
+            "/user/:name",
+            "/user/:id<u64>",
+            "/file/:name<[a-z0-9_-]+>",
+            "/v:version<\\d+>/items",
+            "/v1/items",
+        ]);
+        //满足约束的优先，不满足时回落到无约束的参数
+        assert_eq!(search_pattern(&root, "/user/42"), "/user/:id<u64>");
+        assert_eq!(search_pattern(&root, "/user/abc"), "/user/:name");
+        assert_eq!(
+            search_pattern(&root, "/file/a_b-1"),
+            "/file/:name<[a-z0-9_-]+>"
+        );
+        assert_eq!(search_pattern(&root, "/file/A.txt"), "");
+        //静态路由仍然优先
+        assert_eq!(search_pattern(&root, "/v1/items"), "/v1/items");
+        assert_eq!(search_pattern(&root, "/v2/items"), "/v:version<\\d+>/items");
+        assert_eq!(search_pattern(&root, "/vx/items"), "");
+        assert_eq!(
+            search_params(&root, "/v12/items"),
+            [("version".to_string(), "12".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_tree_node_routes() {
+        let root = build_tree(&["/user/:id", "/", "/user/list", "/static/*file"]);
+        let mut patterns: Vec<_> = root.routes().iter().map(|r| r.pattern.as_str()).collect();
+        patterns.sort_unstable();
+        assert_eq!(patterns, ["/", "/static/*file", "/user/:id", "/user/list"]);
+    }
+
+    #[test]
+    fn test_tree_node_route_mut() {
+        let mut root = build_tree(&["/user/:id", "/user/list/"]);
+        assert!(root.route_mut("/user/list").is_some());
+        assert!(root.route_mut("/user/:id").is_some());
+        assert!(root.route_mut("/user/:name").is_none());
+        assert!(root.route_mut("/user/7").is_none());
     }
 }
@@ -1,9 +1,28 @@
+use std::borrow::Cow;
+
+use super::param;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+//路径片段中需要编码的字符
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
 //根据'/'路径分割：如：/p/blog切分后[p,blog]
 pub(crate) fn parse_pattern(pattern: &str) -> Vec<&str> {
     let mut parts = Vec::new();
-    let list = pattern.split('/').into_iter().collect::<Vec<&str>>();
+    let list = pattern.split('/').collect::<Vec<&str>>();
     for part in list {
-        if part != "" {
+        if !part.is_empty() {
             parts.push(part);
             if part.starts_with("*") {
                 break;
@@ -12,3 +31,192 @@ pub(crate) fn parse_pattern(pattern: &str) -> Vec<&str> {
     }
     parts
 }
+
+//路由中参数名（按出现顺序）：/user/:id<u64>/*path => [id,path]
+pub(crate) fn param_names(pattern: &str) -> impl Iterator<Item = &str> {
+    parse_pattern(pattern)
+        .into_iter()
+        .filter_map(|part| match param::parse_param(part) {
+            Some(param) => param.ok().map(|param| param.name),
+            None => part.strip_prefix('*'),
+        })
+}
+
+//清理请求路径：合并多余的'/'，处理'.'与'..'，去掉末尾的'/'，如：/a//b/../c/ => /a/c
+//已是规范形式的路径（绝大多数请求）不分配内存
+pub(crate) fn clean_path(path: &str) -> Cow<'_, str> {
+    let clean = path == "/"
+        || path.strip_prefix('/').is_some_and(|rest| {
+            rest.split('/')
+                .all(|segment| !matches!(segment, "" | "." | ".."))
+        });
+    if clean {
+        return Cow::Borrowed(path);
+    }
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Cow::Owned(format!("/{}", segments.join("/")))
+}
+
+//请求路径的规范形式：末尾'/'与路由保持一致，通配符路由保留请求中的末尾'/'
+pub(crate) fn canonical_path<'a>(clean: &'a str, path: &str, pattern: &str) -> Cow<'a, str> {
+    let catchall = parse_pattern(pattern)
+        .last()
+        .is_some_and(|part| part.starts_with('*'));
+    let trailing = if catchall {
+        path.ends_with('/')
+    } else {
+        pattern.ends_with('/')
+    };
+    if trailing && clean != "/" {
+        Cow::Owned(format!("{}/", clean))
+    } else {
+        Cow::Borrowed(clean)
+    }
+}
+
+//路径参数解码：%E4%BD%A0 => 你，非utf-8字节按替换字符处理
+pub(crate) fn decode_param(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+//参数值解码后不能包含'..'片段：clean_path只处理未编码的'..'，/static/%2e%2e/etc/passwd、/static/..%2fetc
+//解码后为../etc/passwd，交给*file等参数会造成目录穿越，因此这样的值不匹配路由
+pub(crate) fn is_safe_param(value: &str) -> bool {
+    !value.contains('%')
+        || !decode_param(value)
+            .split(['/', '\\'])
+            .any(|segment| segment == "..")
+}
+
+//根据路由生成url：/user/:id + [(id,7),(tab,posts)] => /user/7?tab=posts
+//缺少路由参数时返回参数名
+pub(crate) fn build_url(pattern: &str, params: &[(&str, &str)]) -> Result<String, String> {
+    let mut used = Vec::new();
+    let mut url = String::new();
+    for part in parse_pattern(pattern) {
+        url.push('/');
+        let name = match param::parse_param(part) {
+            Some(param) => param.ok().map(|param| {
+                //带前缀的参数：v:version<\d+> => v1
+                url.push_str(param.prefix);
+                param.name
+            }),
+            None => part.strip_prefix('*'),
+        };
+        let name = match name {
+            Some(name) => name,
+            None => {
+                url.push_str(part);
+                continue;
+            }
+        };
+        let value = params
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| name.to_string())?;
+        used.push(name);
+        if part.starts_with('*') {
+            //通配符参数保留'/'
+            let segments: Vec<String> = value
+                .split('/')
+                .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+                .collect();
+            url.push_str(&segments.join("/"));
+        } else {
+            url.extend(utf8_percent_encode(value, SEGMENT));
+        }
+    }
+    if url.is_empty() || (pattern.len() > 1 && pattern.ends_with('/')) {
+        url.push('/');
+    }
+    let query: Vec<_> = params
+        .iter()
+        .filter(|(key, _)| !used.contains(key))
+        .collect();
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(&serde_urlencoded::to_string(query).unwrap_or_default());
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_path() {
+        assert_eq!(clean_path("/"), "/");
+        assert_eq!(clean_path(""), "/");
+        assert_eq!(clean_path("/user//info/"), "/user/info");
+        assert_eq!(clean_path("/user/./info"), "/user/info");
+        assert_eq!(clean_path("/static/../user/info"), "/user/info");
+        assert_eq!(clean_path("/../../etc/passwd"), "/etc/passwd");
+        //规范路径不分配内存
+        assert!(matches!(clean_path("/user/info"), Cow::Borrowed(_)));
+        assert!(matches!(clean_path("/user/info/"), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_canonical_path() {
+        assert_eq!(
+            canonical_path("/user/info", "/user/info/", "/user/info"),
+            "/user/info"
+        );
+        assert_eq!(canonical_path("/user", "/user", "/user/"), "/user/");
+        assert_eq!(
+            canonical_path("/static/a", "/static/a/", "/static/*file"),
+            "/static/a/"
+        );
+        assert_eq!(canonical_path("/", "//", "/"), "/");
+        assert_eq!(
+            canonical_path("/file/a", "/file/a/", "/file/:name<[a-z]*>"),
+            "/file/a"
+        );
+    }
+
+    #[test]
+    fn test_is_safe_param() {
+        assert!(is_safe_param("css/app.css"));
+        assert!(is_safe_param("a%2Fb"));
+        assert!(is_safe_param("%2e%2e.txt"));
+        assert!(!is_safe_param("%2e%2e"));
+        assert!(!is_safe_param("%2E%2E/%2e%2e/etc/passwd"));
+        assert!(!is_safe_param("..%2fetc"));
+        assert!(!is_safe_param("a%5C..%5Cb"));
+    }
+
+    #[test]
+    fn test_decode_param() {
+        assert_eq!(decode_param("%E4%BD%A0"), "你");
+        assert_eq!(decode_param("a%2Fb"), "a/b");
+        assert_eq!(decode_param("100%"), "100%");
+    }
+
+    #[test]
+    fn test_build_url() {
+        let url = build_url("/user/:id", &[("id", "7")]).unwrap();
+        assert_eq!(url, "/user/7");
+        let url = build_url("/user/:id/", &[("id", "a b"), ("tab", "x&y")]).unwrap();
+        assert_eq!(url, "/user/a%20b/?tab=x%26y");
+        let url = build_url("/static/*file", &[("file", "css/你.css")]).unwrap();
+        assert_eq!(url, "/static/css/%E4%BD%A0.css");
+        assert_eq!(build_url("/", &[]).unwrap(), "/");
+        assert_eq!(build_url("/user/:id", &[]).unwrap_err(), "id");
+        let url = build_url(
+            "/v:version<\\d+>/items/:id<u64>",
+            &[("version", "2"), ("id", "7")],
+        );
+        assert_eq!(url.unwrap(), "/v2/items/7");
+    }
+}
@@ -1,19 +1,59 @@
-use std::{collections::HashMap, convert::Infallible, sync::Arc};
+use std::{any::TypeId, collections::HashMap, convert::Infallible, sync::Arc};
 
-use hyper::{Body, Method, Request, Response};
-use log::{debug, trace};
+use hyper::{
+    header::{self, HeaderValue},
+    Body, Method, Request, Response, StatusCode,
+};
+use log::{debug, trace, warn};
 
 use crate::{
+    context::States,
+    error::ErrorRenderer,
     middleware::recovery::recovery,
-    router::{handler::Handler, router::Router},
-    Context, RouterGroup,
+    response::write_response,
+    router::{
+        handler::{from_fn, into_handler, Handler, HandlerFn, IntoHandler},
+        host::normalize_host,
+        router::{Router, ANY_METHODS},
+        trie::{Chain, Route},
+        utils, GroupId,
+    },
+    Context, Error, RouteInfo, RouterGroup,
 };
 //web处理引擎（其实代码安全可以移入Router），req参数简单解析
 pub struct Engine {
     pub(crate) router: Router,
-    pub(crate) groups: HashMap<String, Vec<Box<Handler>>>,
+    pub(crate) groups: HashMap<GroupId, Vec<Arc<Handler>>>,
     //全局中间件
-    pub(crate) middlewares: Vec<Box<Handler>>,
+    pub(crate) middlewares: Vec<Arc<Handler>>,
+    //应用级共享状态
+    pub(crate) states: States,
+    //自定义错误渲染器
+    pub(crate) error_renderer: Option<Arc<ErrorRenderer>>,
+    //路径在其他请求方法下有路由时返回405（否则按404处理）
+    pub(crate) handle_method_not_allowed: bool,
+    //自定义405处理
+    pub(crate) no_method: Option<Arc<Handler>>,
+    //自定义404处理
+    pub(crate) no_route: Option<Arc<Handler>>,
+    //分组内的404处理：分组 => handler
+    pub(crate) group_no_routes: HashMap<GroupId, Arc<Handler>>,
+    //非规范请求路径的处理策略
+    pub(crate) path_policy: PathPolicy,
+    //请求体（json、表单等）最大字节数，超过时返回413
+    pub(crate) max_body_size: u64,
+}
+
+//非规范请求路径（多余的'/'、'.'、'..'，末尾'/'与路由不一致）的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathPolicy {
+    //重定向到规范路径：GET/HEAD返回301，其他方法返回308（保留请求方法与请求体）
+    #[default]
+    Redirect,
+    //按清理后的路径直接匹配
+    Lenient,
+    //只匹配规范路径，否则404
+    Strict,
 }
 
 pub fn new() -> Engine {
@@ -21,15 +61,18 @@ pub fn new() -> Engine {
         router: Router::new(),
         groups: HashMap::new(),
         middlewares: Vec::new(),
+        states: HashMap::new(),
+        error_renderer: None,
+        handle_method_not_allowed: true,
+        no_method: None,
+        no_route: None,
+        group_no_routes: HashMap::new(),
+        path_policy: PathPolicy::default(),
+        max_body_size: 2 << 20,
     }
 }
 pub fn default() -> Engine {
-    let e = Engine {
-        router: Router::new(),
-        groups: HashMap::new(),
-        middlewares: Vec::new(),
-    };
-    e.hooks(recovery)
+    new().hooks(recovery)
 }
 
 impl Engine {
@@ -38,115 +81,474 @@ impl Engine {
         req: Request<Body>,
         engin: Arc<Engine>,
     ) -> Result<Response<Body>, Infallible> {
-        let mut context = Context::build_request(&req);
-        let (node, params) = engin
+        let mut context = Context::build_request(req, engin.clone());
+        //按清理后的路径匹配路由：/user//info/、/static/../user/info => /user/info
+        let clean = utils::clean_path(&context.path);
+        //请求的host：Host头，HTTP/2中为uri中的authority；只在注册了按Host路由时解析
+        let host = engin.router.has_hosts().then(|| {
+            let host = context
+                .request
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .or_else(|| context.request.uri().host())
+                .unwrap_or_default();
+            normalize_host(host)
+        });
+        let mut matched = engin
             .router
-            .get_route(req.method().as_str(), req.uri().path());
-        trace!("路径中的参数：{:#?}", &params);
-        context.params = params;
-
-        //添加全局中间件
-        let mut middlewares = engin.get_middlewares();
-        if let Some(node) = node {
-            debug!("请示对应的路由节点:{:#?}", &node);
-            let key = format!(
-                "{}_{}",
-                req.method().as_str(),
-                node.pattern.as_ref().unwrap()
-            );
-            if let Some(group_id) = &node.group_id {
-                //添加分组中间件
-                middlewares.extend(engin.get_middlewares_by_group_id(group_id));
-            }
-            if let Some(handler) = engin.router.handlers.get(&key) {
-                if node.middlewares.len() > 0 {
-                    //添加节点本身的中间件
-                    middlewares.extend(node.get_middlewares());
+            .get_route(&context.method, host.as_deref(), &clean);
+        //未注册HEAD路由时HEAD请求使用GET路由
+        let mut head_as_get = false;
+        if matched.is_none() && context.method == Method::HEAD.as_str() {
+            matched = engin
+                .router
+                .get_route(Method::GET.as_str(), host.as_deref(), &clean);
+            head_as_get = true;
+        }
+        //请求路径不是规范形式：按配置的策略重定向或不匹配
+        let mut strict_miss = false;
+        if let Some(m) = &matched {
+            let canonical = utils::canonical_path(&clean, &context.path, &m.route.pattern);
+            if canonical != context.path {
+                match engin.path_policy {
+                    PathPolicy::Redirect => {
+                        //同样经过全局中间件
+                        let mut middlewares = engin.get_middlewares();
+                        middlewares.push(redirect(canonical.into_owned()));
+                        context.handlers = middlewares.into();
+                        context.next().await;
+                        return Ok(context.response);
+                    }
+                    PathPolicy::Strict => {
+                        matched = None;
+                        strict_miss = true;
+                    }
+                    PathPolicy::Lenient => {}
                 }
-                //添加路由本身handler
-                middlewares.push(handler.as_ref());
-                //设置本次上下文能执行的handler
-                context.handlers.extend(middlewares);
             }
-            
+        }
+
+        if let Some(m) = matched {
+            let route = m.route;
+            debug!("请示对应的路由:{}", &route.pattern);
+            //path_params、params为解码后的值，原始值保存在raw_params中
+            for (name, value) in m.params() {
+                context
+                    .path_params
+                    .push((name.to_string(), utils::decode_param(value)));
+                context
+                    .raw_params
+                    .insert(name.to_string(), value.to_string());
+            }
+            context.params = context.path_params.iter().cloned().collect();
+            //host中的参数：通过Context::host_param读取，不会覆盖同名的路径参数
+            for (name, value) in m.host_params() {
+                context
+                    .host_params
+                    .insert(name.to_string(), value.to_string());
+            }
+            trace!("路径中的参数：{:#?}", &context.params);
+            //设置本次上下文能执行的handler
+            context.handlers = engin.route_chain(route);
+
             debug!("请求上下文:{:#?}", &context);
             //执行用户业务逻辑handler
-            context.next();
+            context.next().await;
             //返回结果（响应）
-            return Ok(context.response);
+            if head_as_get {
+                return Ok(strip_body(context.response).await);
+            }
+            Ok(context.response)
         } else {
-            //执行中间件功能：主要执行全局中间件功能：日志中间件
-            context.next();
-            return Ok(Response::new(Body::from("404 not found")));
+            //添加全局中间件
+            let mut middlewares = engin.get_middlewares();
+            let allowed = engin.allowed_methods(host.as_deref(), &clean, &context.path);
+            let fallback = if allowed.is_empty() || strict_miss {
+                None
+            } else if context.method == Method::OPTIONS.as_str() {
+                //OPTIONS：未注册OPTIONS路由时自动返回204
+                Some(into_handler(options))
+            } else if engin.handle_method_not_allowed {
+                //405：路径存在但请求方法不匹配
+                Some(
+                    engin
+                        .no_method
+                        .clone()
+                        .unwrap_or_else(|| into_handler(method_not_allowed)),
+                )
+            } else {
+                None
+            };
+            if let Some(handler) = fallback {
+                //同样经过全局中间件
+                context.set_header(header::ALLOW.as_str(), &allowed.join(", "));
+                middlewares.push(handler);
+                context.handlers = middlewares.into();
+                context.next().await;
+                return Ok(context.response);
+            }
+            //404：同样经过全局中间件，分组内的路径还会经过分组中间件
+            let matched_host = host
+                .as_deref()
+                .and_then(|host| engin.router.match_host(host));
+            let (group_id, handler) = engin.no_route_handler(matched_host, &clean);
+            if let Some(group_id) = group_id {
+                middlewares.extend(engin.get_middlewares_by_group_id(group_id));
+            }
+            middlewares.push(handler);
+            context.handlers = middlewares.into();
+            context.next().await;
+            Ok(context.response)
         }
     }
 
-    fn add_route<H>(&mut self, method: &str, pattern: &str, handler: H)
+    //路由完整的调用链：1）全局中间件 2）分组中间件 3）节点中间件 4）路由handler
+    //Engine放入Arc后不再修改，因此首次匹配时解析并保存在叶子节点上，之后的请求直接复用
+    fn route_chain(&self, route: &Route) -> Chain {
+        route
+            .chain
+            .get_or_init(|| {
+                let group = route
+                    .group_id
+                    .as_ref()
+                    .and_then(|group_id| self.groups.get(group_id));
+                self.middlewares
+                    .iter()
+                    .chain(group.into_iter().flatten())
+                    .chain(&route.middlewares)
+                    .chain(std::iter::once(&route.handler))
+                    .cloned()
+                    .collect()
+            })
+            .clone()
+    }
+
+    //路径对应的404处理：前缀最长的分组no_route（前缀相同时按Host路由的分组优先），其次为Engine::no_route，默认返回Error::NotFound
+    //host：请求匹配到的host路由，其他host的分组不参与匹配
+    fn no_route_handler(
+        &self,
+        host: Option<&str>,
+        clean: &str,
+    ) -> (Option<&GroupId>, Arc<Handler>) {
+        let group = self
+            .group_no_routes
+            .iter()
+            .filter_map(|(group_id, handler)| {
+                let group_host = group_id.host.as_str();
+                if !group_host.is_empty() && Some(group_host) != host {
+                    return None;
+                }
+                let prefix = group_id.prefix.trim_end_matches('/');
+                clean
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                    .then_some((group_id, handler, (prefix.len(), !group_host.is_empty())))
+            })
+            .max_by_key(|(_, _, rank)| *rank)
+            .map(|(group_id, handler, _)| (group_id, handler));
+        match group {
+            Some((group_id, handler)) => (Some(group_id), handler.clone()),
+            None => (
+                None,
+                self.no_route
+                    .clone()
+                    .unwrap_or_else(|| into_handler(not_found)),
+            ),
+        }
+    }
+
+    //路径可用的请求方法（Allow头）：已注册的方法，注册了GET时包括HEAD，以及OPTIONS
+    fn allowed_methods(&self, host: Option<&str>, clean: &str, path: &str) -> Vec<&str> {
+        let strict = self.path_policy == PathPolicy::Strict;
+        let mut methods = self.router.allowed_methods(host, clean, |pattern| {
+            !strict || utils::canonical_path(clean, path, pattern) == path
+        });
+        if methods.is_empty() {
+            return methods;
+        }
+        for method in [Method::HEAD.as_str(), Method::OPTIONS.as_str()] {
+            let auto =
+                method == Method::OPTIONS.as_str() || methods.contains(&Method::GET.as_str());
+            if auto && !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        methods.sort_unstable();
+        methods
+    }
+
+    fn add_route<H, M>(&mut self, method: &str, pattern: &str, handler: H)
     where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.router.add_route(method, pattern, None, handler);
     }
 
-    pub fn get<S, H>(mut self, pattern: S, handler: H) -> Self
+    pub fn get<S, H, M>(mut self, pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::GET.as_str(), pattern.as_ref(), handler);
         self
     }
 
-    pub fn post<S, H>(mut self, pattern: S, handler: H) -> Self
+    pub fn post<S, H, M>(mut self, pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::POST.as_str(), pattern.as_ref(), handler);
         self
     }
 
-    pub fn put<S, H>(mut self, pattern: S, handler: H) -> Self
+    pub fn put<S, H, M>(mut self, pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::PUT.as_str(), pattern.as_ref(), handler);
         self
     }
 
-    pub fn delete<S, H>(mut self, pattern: S, handler: H) -> Self
+    pub fn delete<S, H, M>(mut self, pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::DELETE.as_str(), pattern.as_ref(), handler);
         self
     }
 
-    pub fn patch<S, H>(mut self, pattern: S, handler: H) -> Self
+    pub fn patch<S, H, M>(mut self, pattern: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: IntoHandler<M>,
     {
         self.add_route(Method::PATCH.as_str(), pattern.as_ref(), handler);
         self
     }
 
+    //未注册HEAD路由时，HEAD请求自动使用GET路由（不返回响应体）
+    pub fn head<S, H, M>(mut self, pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_route(Method::HEAD.as_str(), pattern.as_ref(), handler);
+        self
+    }
+
+    //未注册OPTIONS路由时，OPTIONS请求自动返回204以及Allow头
+    pub fn options<S, H, M>(mut self, pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.add_route(Method::OPTIONS.as_str(), pattern.as_ref(), handler);
+        self
+    }
+
+    //任意请求方法：handle("PROPFIND", "/dav/*path", handler)
+    pub fn handle<S, H, M>(mut self, method: &str, pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.router
+            .add_methods(&[method], pattern.as_ref(), None, handler);
+        self
+    }
+
+    //注册到全部常用请求方法：GET、POST、PUT、PATCH、HEAD、OPTIONS、DELETE、CONNECT、TRACE
+    pub fn any<S, H, M>(mut self, pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.router
+            .add_methods(&ANY_METHODS, pattern.as_ref(), None, handler);
+        self
+    }
+
+    //同一handler注册到多个请求方法：match_methods(&["GET", "POST"], "/search", handler)
+    pub fn match_methods<S, H, M>(mut self, methods: &[&str], pattern: S, handler: H) -> Self
+    where
+        S: AsRef<str>,
+        H: IntoHandler<M>,
+    {
+        self.router
+            .add_methods(methods, pattern.as_ref(), None, handler);
+        self
+    }
+
+    //为最近注册的路由命名：.get("/user/:id", h).name("user.show")，通过Context::url_for生成url
+    pub fn name(mut self, name: &str) -> Self {
+        self.router.name_last_route(None, name);
+        self
+    }
+
     //路由分组
-    pub fn group<S>(&mut self, prefix: S) -> RouterGroup
+    pub fn group<S>(&mut self, prefix: S) -> RouterGroup<'_>
     where
         S: AsRef<str>,
     {
-        self.groups.insert(prefix.as_ref().to_string(), Vec::new());
-        RouterGroup::new(prefix.as_ref(), self)
+        self.groups
+            .entry(GroupId::new(None, prefix.as_ref()))
+            .or_default();
+        RouterGroup::new(prefix.as_ref(), None, self)
+    }
+
+    //按Host路由：只匹配该host（忽略端口与大小写）的请求，其他请求按不区分host的路由匹配
+    //带参数的host，如：:tenant.example.com，参数通过Context::host_param读取
+    //.host("admin.example.com").get("/", handler)
+    pub fn host<S>(&mut self, host: S) -> RouterGroup<'_>
+    where
+        S: AsRef<str>,
+    {
+        let host = host.as_ref().to_ascii_lowercase();
+        self.router.router_mut(Some(&host));
+        self.groups
+            .entry(GroupId::new(Some(&host), ""))
+            .or_default();
+        RouterGroup::new("", Some(&host), self)
+    }
+
+    //挂载子Engine：路由加上前缀，子Engine的全局中间件只作用于其路由以及前缀下的404
+    //子Engine的共享状态合并进来（同类型以当前Engine为准），错误渲染、405、路径策略等配置以当前Engine为准
+    //路由、路由名称或no_route冲突时panic
+    pub fn nest(mut self, prefix: &str, sub: Engine) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        if !prefix.is_empty() && !prefix.starts_with('/') {
+            panic!("路径必须以'/'开头:{}", prefix)
+        }
+        self.mount(prefix, sub);
+        self
+    }
+
+    //合并另一个Engine的路由，规则同nest
+    pub fn merge(mut self, other: Engine) -> Self {
+        self.mount("", other);
+        self
+    }
+
+    //子Engine的分组放入新的scope：与当前Engine（以及其他子Engine）前缀相同的分组互不影响
+    //子Engine的全局中间件作为其根分组的中间件，并加在其各分组中间件之前
+    fn mount(&mut self, prefix: &str, sub: Engine) {
+        let mut scopes: HashMap<usize, usize> = HashMap::new();
+        let mut nest_group = |group_id: Option<&GroupId>| {
+            let group_id = group_id.cloned().unwrap_or_default();
+            let scope = *scopes
+                .entry(group_id.scope)
+                .or_insert_with(GroupId::next_scope);
+            group_id.nest(prefix, scope)
+        };
+        self.router.mount(prefix, sub.router, &mut nest_group);
+        for (group_id, middlewares) in sub.groups {
+            let chain = sub.middlewares.iter().cloned().chain(middlewares);
+            self.groups
+                .entry(nest_group(Some(&group_id)))
+                .or_default()
+                .extend(chain);
+        }
+        let root = nest_group(None);
+        if let Some(handler) = sub.no_route {
+            if prefix.is_empty() && self.no_route.is_some() {
+                panic!("no_route重复注册")
+            }
+            self.add_group_no_route(root.clone(), handler);
+        }
+        for (group_id, handler) in sub.group_no_routes {
+            self.add_group_no_route(nest_group(Some(&group_id)), handler);
+        }
+        self.groups
+            .entry(root)
+            .or_insert_with(|| sub.middlewares.clone());
+        for (type_id, state) in sub.states {
+            self.states.entry(type_id).or_insert(state);
+        }
+    }
+
+    //同一host下前缀相同的no_route只能有一个（不区分挂载的scope）
+    pub(crate) fn add_group_no_route(&mut self, group_id: GroupId, handler: Arc<Handler>) {
+        if self
+            .group_no_routes
+            .keys()
+            .any(|g| g.host == group_id.host && g.prefix == group_id.prefix)
+        {
+            panic!("no_route重复注册:{}{}", group_id.host, group_id.prefix)
+        }
+        self.group_no_routes.insert(group_id, handler);
+    }
+    //注册应用级共享状态（如service、数据库连接池），handler与中间件中通过Context::state::<T>()读取
+    //同一类型只保存一份，重复注册时覆盖
+    pub fn with_state<T>(mut self, state: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        if self
+            .states
+            .insert(TypeId::of::<T>(), Arc::new(state))
+            .is_some()
+        {
+            warn!("共享状态重复注册，已覆盖:{}", std::any::type_name::<T>());
+        }
+        self
+    }
+
+    //自定义错误响应：参数提取失败、handler返回Error等情况统一由此渲染，如：返回统一的json格式
+    pub fn error_renderer<F>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&Context, &Error) -> Response<Body> + Send + Sync + 'static,
+    {
+        self.error_renderer = Some(Arc::new(renderer));
+        self
+    }
+
+    //路径在其他请求方法下有路由时是否返回405（带Allow头），默认开启；关闭后按404处理
+    pub fn handle_method_not_allowed(mut self, enable: bool) -> Self {
+        self.handle_method_not_allowed = enable;
+        self
+    }
+
+    //非规范请求路径的处理策略，默认重定向
+    pub fn path_policy(mut self, policy: PathPolicy) -> Self {
+        self.path_policy = policy;
+        self
+    }
+
+    //请求体最大字节数，默认2M：Context::body_bytes、bind_json、表单等读取请求体时超过限制返回413
+    //multipart的限制通过MultipartConfig设置
+    pub fn max_body_size(mut self, limit: u64) -> Self {
+        self.max_body_size = limit;
+        self
+    }
+
+    //自定义405响应：Allow头已设置在c.response中
+    pub fn no_method<H, M>(mut self, handler: H) -> Self
+    where
+        H: IntoHandler<M>,
+    {
+        self.no_method = Some(handler.into_handler());
+        self
+    }
+
+    //自定义404响应：未匹配到路由时执行，同样经过全局中间件
+    pub fn no_route<H, M>(mut self, handler: H) -> Self
+    where
+        H: IntoHandler<M>,
+    {
+        self.no_route = Some(handler.into_handler());
+        self
     }
+
     //添加中间件
     pub fn hooks<H>(mut self, handler: H) -> Self
     where
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: for<'a> HandlerFn<'a>,
     {
-        self.middlewares.push(Box::new(handler));
+        self.middlewares.push(into_handler(handler));
         self
     }
 
@@ -154,28 +556,1461 @@ impl Engine {
     pub fn add_hooks<S, H>(mut self, pattern: S, method: S, handler: H) -> Self
     where
         S: AsRef<str>,
-        H: Fn(&mut Context) + Send + Sync + 'static,
+        H: for<'a> HandlerFn<'a>,
     {
         self.router
             .add_hooks(pattern.as_ref(), method.as_ref(), handler);
         self
     }
 
-    fn get_middlewares_by_group_id(&self, group_id: &str) -> Vec<&Handler> {
-        let mut middlewares: Vec<&Handler> = Vec::new();
-        if let Some(handlers) = self.groups.get(group_id) {
-            for handler in handlers.iter() {
-                middlewares.push(handler.as_ref());
+    //已注册的路由，按host、路由、请求方法排序
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        self.router
+            .routes()
+            .into_iter()
+            .map(|(host, method, route)| {
+                let group = route
+                    .group_id
+                    .as_ref()
+                    .and_then(|group_id| self.groups.get(group_id));
+                //按Host路由的分组只显示前缀
+                let group_prefix = route
+                    .group_id
+                    .as_ref()
+                    .map(|group_id| group_id.prefix.as_str())
+                    .filter(|prefix| !prefix.is_empty());
+                RouteInfo {
+                    host: host.map(String::from),
+                    method: method.to_string(),
+                    pattern: route.pattern.clone(),
+                    group: group_prefix.map(String::from),
+                    middlewares: self.middlewares.len()
+                        + group.map_or(0, Vec::len)
+                        + route.middlewares.len(),
+                    name: route.name.clone(),
+                }
+            })
+            .collect()
+    }
+
+    //路由表（启动时打印），如：
+    //HOST  METHOD  PATTERN   GROUP  MIDDLEWARES  NAME
+    //-     GET     /user/:id /user  2            user.show
+    pub fn route_table(&self) -> String {
+        let header = ["HOST", "METHOD", "PATTERN", "GROUP", "MIDDLEWARES", "NAME"];
+        let rows: Vec<[String; 6]> = self
+            .routes()
+            .into_iter()
+            .map(|route| {
+                [
+                    route.host.unwrap_or_else(|| "-".to_string()),
+                    route.method,
+                    route.pattern,
+                    route.group.unwrap_or_else(|| "-".to_string()),
+                    route.middlewares.to_string(),
+                    route.name.unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut table = String::new();
+        let header = header.map(String::from);
+        for row in std::iter::once(&header).chain(&rows) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            table.push_str(line.join("  ").trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    fn get_middlewares_by_group_id(&self, group_id: &GroupId) -> Vec<Arc<Handler>> {
+        self.groups.get(group_id).cloned().unwrap_or_default()
+    }
+
+    fn get_middlewares(&self) -> Vec<Arc<Handler>> {
+        self.middlewares.to_vec()
+    }
+}
+
+//默认404处理
+async fn not_found(_c: &mut Context) -> Error {
+    Error::NotFound
+}
+
+//默认405处理
+async fn method_not_allowed(_c: &mut Context) -> Error {
+    Error::MethodNotAllowed
+}
+
+//重定向到规范路径，保留查询参数
+fn redirect(path: String) -> Arc<Handler> {
+    into_handler(from_fn(move |c| {
+        let resp = redirect_response(c, &path);
+        Box::pin(async move { write_response(c, resp) })
+    }))
+}
+
+fn redirect_response(c: &Context, path: &str) -> Response<Body> {
+    let location = match c.request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let status = if c.method == Method::GET.as_str() || c.method == Method::HEAD.as_str() {
+        StatusCode::MOVED_PERMANENTLY
+    } else {
+        StatusCode::PERMANENT_REDIRECT
+    };
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = status;
+    if let Ok(location) = HeaderValue::from_str(&location) {
+        resp.headers_mut().insert(header::LOCATION, location);
+    }
+    resp
+}
+
+//默认OPTIONS处理：Allow头已设置
+async fn options(c: &mut Context) {
+    *c.response.status_mut() = StatusCode::NO_CONTENT;
+}
+
+//HEAD请求：丢弃响应体，保留响应头并设置Content-Length
+async fn strip_body(resp: Response<Body>) -> Response<Body> {
+    let (mut parts, body) = resp.into_parts();
+    if !parts.headers.contains_key(header::CONTENT_LENGTH) {
+        match hyper::body::to_bytes(body).await {
+            Ok(bytes) => {
+                parts
+                    .headers
+                    .insert(header::CONTENT_LENGTH, HeaderValue::from(bytes.len()));
             }
+            Err(e) => warn!("HEAD请求读取响应体失败:{}", e),
         }
-        middlewares
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyper::body;
+
+    use super::*;
+
+    async fn call(engin: &Arc<Engine>, method: Method, uri: &str) -> Response<Body> {
+        call_with_body(engin, method, uri, Body::empty()).await
+    }
+
+    async fn call_with_body(
+        engin: &Arc<Engine>,
+        method: Method,
+        uri: &str,
+        body: Body,
+    ) -> Response<Body> {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(body)
+            .unwrap();
+        Engine::handler(req, engin.clone()).await.unwrap()
+    }
+
+    async fn body_string(resp: Response<Body>) -> String {
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn wrap(c: &mut Context) {
+        c.next().await;
+        //下游handler已执行完成
+        let body = if c.response.headers().contains_key("x-handled") {
+            "wrapped"
+        } else {
+            "not wrapped"
+        };
+        c.set_header("x-wrap", body);
+    }
+
+    async fn slow(c: &mut Context) {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        c.set_header("x-handled", "1");
+        c.string(None, "slow");
+    }
+
+    #[tokio::test]
+    async fn test_async_middleware_wraps_handler() {
+        let engin = Arc::new(default().hooks(wrap).get("/slow", slow));
+        let resp = call(&engin, Method::GET, "/slow").await;
+        assert_eq!(resp.headers()["x-wrap"], "wrapped");
+        assert_eq!(body_string(resp).await, "slow");
+    }
+
+    #[tokio::test]
+    async fn test_async_closure_handler() {
+        let engin = Arc::new(new().get("/hello/:name", async |c: &mut Context| {
+            let name = c.param::<String>("name").unwrap();
+            c.string(None, &format!("hello {}", name));
+        }));
+        let resp = call(&engin, Method::GET, "/hello/rdd").await;
+        assert_eq!(body_string(resp).await, "hello rdd");
     }
 
-    fn get_middlewares(&self) -> Vec<&Handler> {
-        let mut middlewares: Vec<&Handler> = Vec::with_capacity(self.middlewares.len());
-        for handler in self.middlewares.iter() {
-            middlewares.push(handler.as_ref());
+    #[tokio::test]
+    async fn test_capturing_closure() {
+        //中间件与handler捕获外部配置
+        let prefix = Arc::new("api".to_string());
+        let tag = prefix.clone();
+        let engin = Arc::new(
+            new()
+                .hooks(from_fn(move |c| {
+                    let tag = tag.clone();
+                    Box::pin(async move {
+                        c.next().await;
+                        c.set_header("x-prefix", &tag);
+                    })
+                }))
+                .get(
+                    "/hello",
+                    from_fn(move |c| {
+                        let prefix = prefix.clone();
+                        Box::pin(async move { c.string(None, &format!("{} hello", prefix)) })
+                    }),
+                ),
+        );
+        let resp = call(&engin, Method::GET, "/hello").await;
+        assert_eq!(resp.headers()["x-prefix"], "api");
+        assert_eq!(body_string(resp).await, "api hello");
+    }
+
+    #[tokio::test]
+    async fn test_recovery_catches_async_panic() {
+        async fn boom(_c: &mut Context) {
+            tokio::task::yield_now().await;
+            panic!("boom");
+        }
+        let engin = Arc::new(default().get("/boom", boom));
+        let resp = call(&engin, Method::GET, "/boom").await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body_string(resp).await, "500 internal server error");
+    }
+
+    #[tokio::test]
+    async fn test_recovery_stops_chain_on_middleware_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static HIT: AtomicUsize = AtomicUsize::new(0);
+        async fn boom(_c: &mut Context) {
+            panic!("boom");
+        }
+        async fn secret(c: &mut Context) {
+            HIT.fetch_add(1, Ordering::SeqCst);
+            c.string(None, "secret");
+        }
+        let engin = Arc::new(default().hooks(boom).get("/x", secret));
+        let resp = call(&engin, Method::GET, "/x").await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body_string(resp).await, "500 internal server error");
+        assert_eq!(HIT.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_body_cached_between_middleware_and_handler() {
+        #[derive(serde::Deserialize)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+        async fn log_body(c: &mut Context) {
+            let body = c.body_string().await.unwrap();
+            c.set_header("x-body-len", &body.len().to_string());
+            c.next().await;
+        }
+        async fn add_user(c: &mut Context) {
+            match c.bind_json::<User>().await {
+                Ok(user) => c.string(None, &format!("{}:{}", user.name, user.age)),
+                Err(e) => c.string(Some(hyper::StatusCode::BAD_REQUEST), &e.to_string()),
+            }
         }
-        middlewares
+        let engin = Arc::new(new().hooks(log_body).post("/user", add_user));
+
+        let json = r#"{"name":"hmm","age":18}"#;
+        let resp = call_with_body(&engin, Method::POST, "/user", Body::from(json)).await;
+        assert_eq!(
+            resp.headers()["x-body-len"],
+            json.len().to_string().as_str()
+        );
+        assert_eq!(body_string(resp).await, "hmm:18");
+
+        let resp = call(&engin, Method::POST, "/user").await;
+        assert_eq!(resp.status(), hyper::StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(resp).await, "missing request body");
+
+        let resp = call_with_body(&engin, Method::POST, "/user", Body::from("{")).await;
+        assert_eq!(resp.status(), hyper::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_max_body_size() {
+        async fn echo(c: &mut Context) -> Result<String, Error> {
+            Ok(c.body_string().await?)
+        }
+        let engin = Arc::new(new().max_body_size(8).post("/echo", echo));
+
+        let resp = call_with_body(&engin, Method::POST, "/echo", Body::from("12345678")).await;
+        assert_eq!(body_string(resp).await, "12345678");
+        //Content-Length超过限制
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/echo")
+            .header(header::CONTENT_LENGTH, "9")
+            .body(Body::from("123456789"))
+            .unwrap();
+        let resp = Engine::handler(req, engin.clone()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        //没有Content-Length的分块请求体：读取过程中超过限制
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            sender.send_data("12345".into()).await.unwrap();
+            let _ = sender.send_data("6789".into()).await;
+        });
+        let resp = call_with_body(&engin, Method::POST, "/echo", body).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body_string(resp).await, "request body exceeds size limit:8");
+    }
+
+    #[tokio::test]
+    async fn test_urlencoded_form() {
+        #[derive(serde::Deserialize)]
+        struct Login {
+            name: String,
+            age: u8,
+        }
+        async fn login(c: &mut Context) {
+            let age = c.form::<u8>("age").await.unwrap();
+            let tags = c.form_array::<String>("tag").await.unwrap();
+            let login = c.bind_form::<Login>().await.unwrap();
+            let body = format!(
+                "{}|{}|{}|{}|{}",
+                c.forms().await.unwrap()["name"],
+                age,
+                tags.join(","),
+                login.name,
+                login.age
+            );
+            c.string(None, &body);
+        }
+        let engin = Arc::new(new().post("/login", login));
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/login")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from("name=%E4%BD%A0&age=18&tag=a&tag=b"))
+            .unwrap();
+        let resp = Engine::handler(req, engin).await.unwrap();
+        assert_eq!(body_string(resp).await, "你|18|a,b|你|18");
+    }
+
+    #[tokio::test]
+    async fn test_form_parsed_lazily() {
+        async fn deny(c: &mut Context) {
+            c.abort_with_status(StatusCode::UNAUTHORIZED);
+        }
+        async fn name(c: &mut Context) -> Result<String, Error> {
+            Ok(c.form::<String>("name").await?)
+        }
+        let form_request = |body: Body| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/login")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(body)
+                .unwrap()
+        };
+        //中间件拒绝的请求不读取请求体：请求体一直未发送完也能立即返回
+        let engin = Arc::new(new().hooks(deny).post("/login", name));
+        let (_sender, body) = Body::channel();
+        let resp = tokio::time::timeout(
+            Duration::from_secs(1),
+            Engine::handler(form_request(body), engin),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        //表单同样受请求体大小限制
+        let engin = Arc::new(new().max_body_size(8).post("/login", name));
+        let resp = Engine::handler(form_request(Body::from("name=a")), engin.clone())
+            .await
+            .unwrap();
+        assert_eq!(body_string(resp).await, "a");
+        let resp = Engine::handler(form_request(Body::from("name=abcdefgh")), engin)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_form() {
+        #[derive(serde::Deserialize)]
+        struct Login {
+            name: String,
+        }
+        async fn name(c: &mut Context) -> Result<String, Error> {
+            Ok(c.form::<String>("name").await?)
+        }
+        async fn login(c: &mut Context) -> Result<String, Error> {
+            Ok(c.bind_form::<Login>().await?.name)
+        }
+        let engin = Arc::new(new().post("/name", name).post("/login", login));
+        let request = |uri: &str, content_type: &str, body: &'static str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let form = "application/x-www-form-urlencoded";
+
+        //非法的UTF-8返回400及具体原因，而不是缺少字段
+        for uri in ["/name", "/login"] {
+            let resp = Engine::handler(request(uri, form, "name=%FF"), engin.clone())
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body = body_string(resp).await;
+            assert!(body.starts_with("failed to deserialize form body("));
+        }
+
+        //bind_form与form一样只解析表单类型的请求体
+        let resp = Engine::handler(request("/login", form, "name=a"), engin.clone())
+            .await
+            .unwrap();
+        assert_eq!(body_string(resp).await, "a");
+        let resp = Engine::handler(request("/login", "application/json", "name=a"), engin)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_string(resp).await,
+            "failed to deserialize form body(missing field `name`)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extractor_handler() {
+        use crate::extract::{Json, Path, Query};
+
+        #[derive(serde::Deserialize)]
+        struct Paging {
+            page: u32,
+        }
+        #[derive(serde::Serialize)]
+        struct User {
+            id: u64,
+            page: u32,
+        }
+        async fn get_user(Path(id): Path<u64>, Query(q): Query<Paging>) -> Json<User> {
+            Json(User { id, page: q.page })
+        }
+        async fn get_file(Path((dir, file)): Path<(String, String)>) -> Json<String> {
+            Json(format!("{}/{}", dir, file))
+        }
+        async fn mark(c: &mut Context) {
+            c.set_header("x-mark", "1");
+            c.next().await;
+        }
+        let engin = Arc::new(
+            new()
+                .hooks(mark)
+                .get("/user/:id", get_user)
+                .get("/file/:dir/*file", get_file),
+        );
+
+        let resp = call(&engin, Method::GET, "/user/7?page=2").await;
+        assert_eq!(resp.status(), hyper::StatusCode::OK);
+        assert_eq!(resp.headers()["x-mark"], "1");
+        assert_eq!(
+            resp.headers()["content-type"],
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(body_string(resp).await, r#"{"id":7,"page":2}"#);
+
+        let resp = call(&engin, Method::GET, "/file/static/js/app.js").await;
+        assert_eq!(body_string(resp).await, r#""static/js/app.js""#);
+
+        let resp = call(&engin, Method::GET, "/user/abc?page=2").await;
+        assert_eq!(resp.status(), hyper::StatusCode::BAD_REQUEST);
+        let resp = call(&engin, Method::GET, "/user/7").await;
+        assert_eq!(resp.status(), hyper::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_handler_return_values() {
+        use crate::{extract::Json, Html, IntoResponse};
+        use hyper::StatusCode;
+
+        struct NotFound;
+        impl IntoResponse for NotFound {
+            fn into_response(self) -> Response<Body> {
+                (StatusCode::NOT_FOUND, "user not found").into_response()
+            }
+        }
+        fn find_user(id: u64) -> Result<String, NotFound> {
+            if id == 7 {
+                Ok("hmm".to_string())
+            } else {
+                Err(NotFound)
+            }
+        }
+        async fn get_user(c: &mut Context) -> Result<Json<String>, NotFound> {
+            let id = c.param::<u64>("id").map_err(|_| NotFound)?;
+            let user = find_user(id)?;
+            Ok(Json(user))
+        }
+        async fn created() -> (StatusCode, &'static str) {
+            (StatusCode::CREATED, "created")
+        }
+        async fn page() -> Html<&'static str> {
+            Html("<h1>hi</h1>")
+        }
+        async fn legacy(c: &mut Context) {
+            c.string(Some(StatusCode::ACCEPTED), "legacy");
+        }
+        let engin = Arc::new(
+            new()
+                .get("/user/:id", get_user)
+                .post("/user", created)
+                .get("/page", page)
+                .get("/legacy", legacy),
+        );
+
+        let resp = call(&engin, Method::GET, "/user/7").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_string(resp).await, r#""hmm""#);
+
+        let resp = call(&engin, Method::GET, "/user/8").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_string(resp).await, "user not found");
+
+        let resp = call(&engin, Method::POST, "/user").await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.headers()["content-type"], "text/plain; charset=utf-8");
+
+        let resp = call(&engin, Method::GET, "/page").await;
+        assert_eq!(resp.headers()["content-type"], "text/html; charset=utf-8");
+
+        let resp = call(&engin, Method::GET, "/legacy").await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(body_string(resp).await, "legacy");
+    }
+
+    #[tokio::test]
+    async fn test_shared_state() {
+        use crate::State;
+
+        struct Config {
+            name: &'static str,
+        }
+        struct Counter(std::sync::atomic::AtomicUsize);
+
+        async fn by_context(c: &mut Context) -> String {
+            let config = c.state::<Config>().unwrap();
+            config.name.to_string()
+        }
+        async fn by_extractor(counter: State<Counter>) -> String {
+            let n = (counter.0)
+                .0
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+                + 1;
+            n.to_string()
+        }
+        async fn missing(_s: State<String>) -> &'static str {
+            "unreachable"
+        }
+        let engin = Arc::new(
+            new()
+                .with_state(Config { name: "rdd" })
+                .with_state(Counter(Default::default()))
+                .get("/name", by_context)
+                .get("/count", by_extractor)
+                .get("/missing", missing),
+        );
+
+        let resp = call(&engin, Method::GET, "/name").await;
+        assert_eq!(body_string(resp).await, "rdd");
+
+        call(&engin, Method::GET, "/count").await;
+        let resp = call(&engin, Method::GET, "/count").await;
+        assert_eq!(body_string(resp).await, "2");
+
+        let resp = call(&engin, Method::GET, "/missing").await;
+        assert_eq!(resp.status(), hyper::StatusCode::INTERNAL_SERVER_ERROR);
+        //缺失状态属于服务端配置错误，类型信息只写入日志
+        assert_eq!(body_string(resp).await, "500 internal server error");
+    }
+
+    #[tokio::test]
+    async fn test_middleware_passes_values_to_handler() {
+        use crate::Extension;
+
+        #[derive(Clone)]
+        struct CurrentUser {
+            id: u64,
+        }
+
+        async fn auth(c: &mut Context) {
+            c.set("request_id", "req-1".to_string());
+            c.extensions_mut().insert(CurrentUser { id: 7 });
+            c.next().await;
+        }
+        async fn by_key(c: &mut Context) {
+            let request_id = c.get::<String>("request_id").cloned().unwrap();
+            //类型不匹配
+            assert!(c.get::<u64>("request_id").is_none());
+            let user_id = c.extensions().get::<CurrentUser>().unwrap().id;
+            c.string(None, &format!("{}:{}", request_id, user_id));
+        }
+        async fn by_extractor(user: Extension<CurrentUser>) -> String {
+            user.id.to_string()
+        }
+        let mut engin = new();
+        engin
+            .group("/admin")
+            .hooks(auth)
+            .get("/key", by_key)
+            .get("/ext", by_extractor);
+        let engin = Arc::new(engin.get("/ext", by_extractor));
+
+        let resp = call(&engin, Method::GET, "/admin/key").await;
+        assert_eq!(body_string(resp).await, "req-1:7");
+        let resp = call(&engin, Method::GET, "/admin/ext").await;
+        assert_eq!(body_string(resp).await, "7");
+        //未经过auth中间件
+        let resp = call(&engin, Method::GET, "/ext").await;
+        assert_eq!(resp.status(), hyper::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_abort_stops_chain() {
+        use hyper::StatusCode;
+
+        async fn observe(c: &mut Context) {
+            c.next().await;
+            let aborted = if c.is_aborted() { "1" } else { "0" };
+            c.set_header("x-aborted", aborted);
+        }
+        async fn deny(c: &mut Context) {
+            c.abort_with_status(StatusCode::UNAUTHORIZED);
+        }
+        async fn deny_json(c: &mut Context) {
+            c.abort_with_json(StatusCode::FORBIDDEN, vec!["no permission"]);
+        }
+        async fn handled(c: &mut Context) {
+            c.string(None, "handled");
+        }
+        let mut engin = new().hooks(observe).get("/open", handled);
+        engin.group("/status").hooks(deny).get("/x", handled);
+        engin.group("/json").hooks(deny_json).get("/x", handled);
+        let engin = Arc::new(engin);
+
+        let resp = call(&engin, Method::GET, "/open").await;
+        assert_eq!(resp.headers()["x-aborted"], "0");
+        assert_eq!(body_string(resp).await, "handled");
+
+        let resp = call(&engin, Method::GET, "/status/x").await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers()["x-aborted"], "1");
+        assert_eq!(body_string(resp).await, "");
+
+        let resp = call(&engin, Method::GET, "/json/x").await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.headers()["x-aborted"], "1");
+        assert_eq!(body_string(resp).await, r#"["no permission"]"#);
+    }
+
+    #[tokio::test]
+    async fn test_error_renderer() {
+        use crate::{extract::Path, Error};
+        use hyper::StatusCode;
+
+        async fn user(c: &mut Context) -> Result<String, Error> {
+            let id = c.param::<u64>("id")?;
+            Ok(id.to_string())
+        }
+        async fn by_path(Path(id): Path<u64>) -> String {
+            id.to_string()
+        }
+        async fn fail() -> Result<String, Error> {
+            Err(Error::internal("db down"))
+        }
+        let routes = |engin: Engine| {
+            engin
+                .get("/user/:id", user)
+                .get("/path/:id", by_path)
+                .get("/fail", fail)
+        };
+
+        //默认渲染：纯文本
+        let engin = Arc::new(routes(new()));
+        let resp = call(&engin, Method::GET, "/user/abc").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = call(&engin, Method::GET, "/fail").await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body_string(resp).await, "500 internal server error");
+
+        //自定义渲染：json
+        let engin = Arc::new(routes(new().error_renderer(|c, e| {
+            let body = format!(r#"{{"code":{},"path":"{}"}}"#, e.status().as_u16(), c.path);
+            let mut resp = Response::new(Body::from(body));
+            *resp.status_mut() = e.status();
+            resp
+        })));
+        let resp = call(&engin, Method::GET, "/user/abc").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_string(resp).await,
+            r#"{"code":400,"path":"/user/abc"}"#
+        );
+        let resp = call(&engin, Method::GET, "/path/abc").await;
+        assert_eq!(
+            body_string(resp).await,
+            r#"{"code":400,"path":"/path/abc"}"#
+        );
+        let resp = call(&engin, Method::GET, "/fail").await;
+        assert_eq!(body_string(resp).await, r#"{"code":500,"path":"/fail"}"#);
+        let resp = call(&engin, Method::GET, "/user/7").await;
+        assert_eq!(body_string(resp).await, "7");
+    }
+
+    #[tokio::test]
+    async fn test_validated_binding() {
+        use crate::{
+            extract::{Json, Query},
+            Error, Valid, Validate, ValidationErrors,
+        };
+        use hyper::StatusCode;
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct Paging {
+            page: u32,
+            size: u32,
+        }
+
+        impl Validate for Paging {
+            fn validate(&self) -> Result<(), ValidationErrors> {
+                let mut v = ValidationErrors::new();
+                v.range("page", self.page, 1, 1000);
+                v.range("size", self.size, 1, 100);
+                v.into_result()
+            }
+        }
+
+        async fn list(Valid(Query(paging)): Valid<Query<Paging>>) -> String {
+            format!("{}-{}", paging.page, paging.size)
+        }
+        async fn add(c: &mut Context) -> Result<Json<u32>, Error> {
+            let paging = c.bind_valid_json::<Paging>().await?;
+            Ok(Json(paging.page))
+        }
+        let engin = Arc::new(new().get("/list", list).post("/add", add));
+
+        let resp = call(&engin, Method::GET, "/list?page=2&size=10").await;
+        assert_eq!(body_string(resp).await, "2-10");
+        let resp = call(&engin, Method::GET, "/list?page=0&size=200").await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body_string(resp).await;
+        assert!(body.contains(r#""field":"page""#) && body.contains(r#""field":"size""#));
+        //绑定失败仍为400
+        let resp = call(&engin, Method::GET, "/list?page=x&size=10").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let body = Body::from(r#"{"page":3,"size":0}"#);
+        let resp = call_with_body(&engin, Method::POST, "/add", body).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = Body::from(r#"{"page":3,"size":5}"#);
+        let resp = call_with_body(&engin, Method::POST, "/add", body).await;
+        assert_eq!(body_string(resp).await, "3");
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed() {
+        use hyper::StatusCode;
+
+        async fn ok(c: &mut Context) {
+            c.string(None, "ok");
+        }
+        async fn log(c: &mut Context) {
+            c.next().await;
+            c.set_header("x-logged", "1");
+        }
+        let routes = |engin: Engine| {
+            engin
+                .hooks(log)
+                .get("/user/:id", ok)
+                .put("/user/:id", ok)
+                .post("/user", ok)
+        };
+
+        let engin = Arc::new(routes(new()));
+        let resp = call(&engin, Method::DELETE, "/user/7").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()["allow"], "GET, HEAD, OPTIONS, PUT");
+        assert_eq!(resp.headers()["x-logged"], "1");
+        let resp = call(&engin, Method::GET, "/user").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()["allow"], "OPTIONS, POST");
+        let resp = call(&engin, Method::GET, "/none").await;
+        assert_ne!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        //关闭405
+        let engin = Arc::new(routes(new().handle_method_not_allowed(false)));
+        let resp = call(&engin, Method::DELETE, "/user/7").await;
+        assert_ne!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(!resp.headers().contains_key("allow"));
+
+        //自定义405
+        let engin = Arc::new(routes(new().no_method(async |c: &mut Context| {
+            let allow = c.response.headers()["allow"].to_str().unwrap().to_string();
+            (StatusCode::METHOD_NOT_ALLOWED, format!("use {}", allow))
+        })));
+        let resp = call(&engin, Method::DELETE, "/user/7").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()["allow"], "GET, HEAD, OPTIONS, PUT");
+        assert_eq!(body_string(resp).await, "use GET, HEAD, OPTIONS, PUT");
+    }
+
+    #[tokio::test]
+    async fn test_auto_head_and_options() {
+        async fn user(c: &mut Context) {
+            c.set_header("x-user", "7");
+            c.string(None, "hello");
+        }
+        async fn cors(c: &mut Context) -> StatusCode {
+            c.set_header("access-control-allow-origin", "*");
+            StatusCode::OK
+        }
+        let mut engin = new().get("/user/:id", user).post("/user/:id", user);
+        engin
+            .group("/api")
+            .get("/item", user)
+            .options("/item", cors);
+        let engin = Arc::new(engin);
+
+        let resp = call(&engin, Method::HEAD, "/user/7").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["x-user"], "7");
+        assert_eq!(resp.headers()["content-length"], "5");
+        assert_eq!(body_string(resp).await, "");
+
+        let resp = call(&engin, Method::OPTIONS, "/user/7").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()["allow"], "GET, HEAD, OPTIONS, POST");
+
+        //用户注册的OPTIONS路由优先
+        let resp = call(&engin, Method::OPTIONS, "/api/item").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["access-control-allow-origin"], "*");
+
+        let resp = call(&engin, Method::OPTIONS, "/none").await;
+        assert_ne!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_custom_and_multiple_methods() {
+        async fn method(c: &mut Context) -> String {
+            c.method.clone()
+        }
+        let mut engin = new()
+            .handle("PROPFIND", "/dav/:name", method)
+            .any("/any", method)
+            .match_methods(&["GET", "POST"], "/search", method);
+        engin.group("/rpc").handle("REPORT", "/status", method);
+        let engin = Arc::new(engin);
+
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+        let resp = call(&engin, propfind, "/dav/a.txt").await;
+        assert_eq!(body_string(resp).await, "PROPFIND");
+        let report = Method::from_bytes(b"REPORT").unwrap();
+        let resp = call(&engin, report, "/rpc/status").await;
+        assert_eq!(body_string(resp).await, "REPORT");
+
+        for m in [Method::GET, Method::POST, Method::DELETE, Method::TRACE] {
+            let resp = call(&engin, m.clone(), "/any").await;
+            assert_eq!(body_string(resp).await, m.as_str());
+        }
+
+        let resp = call(&engin, Method::POST, "/search").await;
+        assert_eq!(body_string(resp).await, "POST");
+        let resp = call(&engin, Method::PUT, "/search").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()["allow"], "GET, HEAD, OPTIONS, POST");
+    }
+
+    #[test]
+    #[should_panic(expected = "请求方法不合法")]
+    fn test_invalid_method() {
+        let _ = new().handle("BAD METHOD", "/x", async |_c: &mut Context| {});
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "route `GET /user/:name` conflicts with existing route `GET /user/:id`"
+    )]
+    fn test_conflicting_route() {
+        let _ = new()
+            .get("/user/:id", async |_c: &mut Context| {})
+            .get("/user/:name", async |_c: &mut Context| {});
+    }
+
+    #[test]
+    fn test_same_pattern_different_methods() {
+        let mut engin = new().get("/user/:id", async |_c: &mut Context| {});
+        engin
+            .group("/user")
+            .post("/:id", async |_c: &mut Context| {});
+        assert_eq!(
+            engin.router.allowed_methods(None, "/user/7", |_| true),
+            vec!["GET", "POST"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_policy() {
+        async fn info(c: &mut Context) -> String {
+            c.path.clone()
+        }
+        async fn hello(c: &mut Context) -> String {
+            let name = c.param::<String>("name").unwrap();
+            format!("{}|{}", name, c.raw_param("name").unwrap())
+        }
+        async fn file(c: &mut Context) -> String {
+            c.param::<String>("file").unwrap()
+        }
+        let routes = |engin: Engine| {
+            engin
+                .get("/user/info", info)
+                .post("/user/info", info)
+                .get("/dir/", info)
+                .get("/hello/:name", hello)
+                .get("/static/*file", file)
+        };
+
+        let engin = Arc::new(routes(new()).hooks(wrap));
+        let resp = call(&engin, Method::GET, "/user//info/?a=1").await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers()["location"], "/user/info?a=1");
+        //重定向同样经过全局中间件
+        assert_eq!(resp.headers()["x-wrap"], "not wrapped");
+        let resp = call(&engin, Method::POST, "/static/../user/info").await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers()["location"], "/user/info");
+        let resp = call(&engin, Method::GET, "/dir").await;
+        assert_eq!(resp.headers()["location"], "/dir/");
+        let resp = call(&engin, Method::GET, "/user/info").await;
+        assert_eq!(body_string(resp).await, "/user/info");
+
+        //参数解码，原始值仍可读取
+        let resp = call(&engin, Method::GET, "/hello/%E4%BD%A0%2F").await;
+        assert_eq!(body_string(resp).await, "你/|%E4%BD%A0%2F");
+        let resp = call(&engin, Method::GET, "/static/a%20b/c").await;
+        assert_eq!(body_string(resp).await, "a b/c");
+        //编码的'..'不能穿越目录
+        let resp = call(&engin, Method::GET, "/static/%2e%2e/%2e%2e/etc/passwd").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = call(&engin, Method::GET, "/static/..%2f..%2fetc%2fpasswd").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = call(&engin, Method::GET, "/hello/%2E%2E").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let engin = Arc::new(routes(new().path_policy(PathPolicy::Lenient)));
+        let resp = call(&engin, Method::GET, "/user//info/").await;
+        assert_eq!(body_string(resp).await, "/user//info/");
+
+        let engin = Arc::new(routes(new().path_policy(PathPolicy::Strict)));
+        let resp = call(&engin, Method::GET, "/user/info/").await;
+        assert_eq!(body_string(resp).await, "404 not found");
+        let resp = call(&engin, Method::PUT, "/user/info/").await;
+        assert_ne!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let resp = call(&engin, Method::GET, "/dir/").await;
+        assert_eq!(body_string(resp).await, "/dir/");
+    }
+
+    #[tokio::test]
+    async fn test_named_routes() {
+        use crate::{Error, UrlForError};
+
+        async fn show(c: &mut Context) -> Result<String, Error> {
+            let url = c.url_for("user.show", &[("id", "7"), ("tab", "posts")])?;
+            let avatar = c.url_for("admin.avatar", &[])?;
+            Ok(format!("{} {}", url, avatar))
+        }
+        async fn missing(c: &mut Context) -> String {
+            let unknown = c.url_for("user.none", &[]).unwrap_err();
+            assert!(matches!(unknown, UrlForError::UnknownRoute { .. }));
+            let missing = c.url_for("user.show", &[]).unwrap_err();
+            missing.to_string()
+        }
+        let mut engin = new()
+            .get("/user/:id", show)
+            .name("user.show")
+            .get("/missing", missing);
+        engin
+            .group("/admin")
+            .post("/avatar", show)
+            .name("admin.avatar");
+        let engin = Arc::new(engin);
+
+        let resp = call(&engin, Method::GET, "/user/1").await;
+        assert_eq!(body_string(resp).await, "/user/7?tab=posts /admin/avatar");
+        let resp = call(&engin, Method::GET, "/missing").await;
+        assert_eq!(
+            body_string(resp).await,
+            "missing param `id` for route `user.show`"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "route name `user` for `/b` is already used by `/a`")]
+    fn test_duplicate_route_name() {
+        let _ = new()
+            .get("/a", async |_c: &mut Context| {})
+            .name("user")
+            .get("/b", async |_c: &mut Context| {})
+            .name("user");
+    }
+
+    #[tokio::test]
+    async fn test_path_param_constraints() {
+        use crate::extract::Path;
+
+        async fn by_id(Path(id): Path<u64>) -> String {
+            format!("id {}", id)
+        }
+        async fn by_name(c: &mut Context) -> String {
+            format!("name {}", c.param::<String>("name").unwrap())
+        }
+        async fn version(c: &mut Context) -> String {
+            format!("version {}", c.param::<u32>("version").unwrap())
+        }
+        let engin = Arc::new(
+            new()
+                .get("/user/:id<u64>", by_id)
+                .get("/user/:name", by_name)
+                .get("/file/:name<[a-z0-9_-]+>", by_name)
+                .get(r"/v:version<\d+>/items", version),
+        );
+
+        let resp = call(&engin, Method::GET, "/user/42").await;
+        assert_eq!(body_string(resp).await, "id 42");
+        let resp = call(&engin, Method::GET, "/user/abc").await;
+        assert_eq!(body_string(resp).await, "name abc");
+        let resp = call(&engin, Method::GET, "/file/a_b-1").await;
+        assert_eq!(body_string(resp).await, "name a_b-1");
+        let resp = call(&engin, Method::GET, "/file/A.txt").await;
+        assert_eq!(body_string(resp).await, "404 not found");
+        let resp = call(&engin, Method::GET, "/v3/items").await;
+        assert_eq!(body_string(resp).await, "version 3");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid route `/user/:id<[a->`")]
+    fn test_invalid_param_constraint() {
+        let _ = new().get("/user/:id<[a->", async |_c: &mut Context| {});
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        use crate::RouteInfo;
+
+        async fn logger(c: &mut Context) {
+            c.next().await;
+        }
+        let mut engin = new()
+            .hooks(logger)
+            .get("/", async |_c: &mut Context| {})
+            .name("home");
+        engin
+            .group("/user")
+            .hooks(logger)
+            .match_methods(&["GET", "POST"], "/:id<u64>", async |_c: &mut Context| {})
+            .name("user.show")
+            //同一路由的其他请求方法与其他host下的同名路由不继承名称
+            .delete("/:id<u64>", async |_c: &mut Context| {});
+        engin.host("a.io").get("/", async |_c: &mut Context| {});
+        let engin = engin.add_hooks("/user/:id<u64>", "POST", logger);
+
+        let route =
+            |method: &str, pattern: &str, group: Option<&str>, middlewares, name: Option<&str>| {
+                RouteInfo {
+                    host: None,
+                    method: method.to_string(),
+                    pattern: pattern.to_string(),
+                    group: group.map(String::from),
+                    middlewares,
+                    name: name.map(String::from),
+                }
+            };
+        assert_eq!(
+            engin.routes(),
+            [
+                route("GET", "/", None, 1, Some("home")),
+                route("DELETE", "/user/:id<u64>", Some("/user"), 2, None),
+                route("GET", "/user/:id<u64>", Some("/user"), 2, Some("user.show")),
+                route(
+                    "POST",
+                    "/user/:id<u64>",
+                    Some("/user"),
+                    3,
+                    Some("user.show")
+                ),
+                RouteInfo {
+                    host: Some("a.io".to_string()),
+                    ..route("GET", "/", None, 1, None)
+                },
+            ]
+        );
+        assert_eq!(
+            engin.route_table(),
+            "HOST  METHOD  PATTERN         GROUP  MIDDLEWARES  NAME\n\
+             -     GET     /               -      1            home\n\
+             -     DELETE  /user/:id<u64>  /user  2            -\n\
+             -     GET     /user/:id<u64>  /user  2            user.show\n\
+             -     POST    /user/:id<u64>  /user  3            user.show\n\
+             a.io  GET     /               -      1            -\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_route() {
+        use crate::extract::Json;
+
+        async fn mark(c: &mut Context) {
+            c.next().await;
+            c.set_header("x-global", "1");
+        }
+        async fn api_mark(c: &mut Context) {
+            c.next().await;
+            c.set_header("x-api", "1");
+        }
+        async fn api_not_found(c: &mut Context) -> (StatusCode, Json<serde_json::Value>) {
+            let path = c.path.clone();
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "not found", "path": path })),
+            )
+        }
+
+        //默认404：真实状态码，全局中间件同样执行
+        let engin = Arc::new(new().hooks(mark).get("/", async |_c: &mut Context| {}));
+        let resp = call(&engin, Method::GET, "/none").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()["x-global"], "1");
+        assert_eq!(body_string(resp).await, "404 not found");
+
+        let mut engin = new()
+            .hooks(mark)
+            .no_route(async |_c: &mut Context| (StatusCode::NOT_FOUND, "custom"));
+        engin
+            .group("/api")
+            .hooks(api_mark)
+            .no_route(api_not_found)
+            .get("/users", async |_c: &mut Context| {});
+        let engin = Arc::new(engin);
+
+        let resp = call(&engin, Method::GET, "/none").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key("x-api"));
+        assert_eq!(body_string(resp).await, "custom");
+        //分组内：分组的no_route，并经过分组中间件
+        let resp = call(&engin, Method::GET, "/api/none").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()["x-global"], "1");
+        assert_eq!(resp.headers()["x-api"], "1");
+        assert_eq!(
+            body_string(resp).await,
+            r#"{"error":"not found","path":"/api/none"}"#
+        );
+        //前缀需按路径片段匹配
+        let resp = call(&engin, Method::GET, "/apis").await;
+        assert_eq!(body_string(resp).await, "custom");
+    }
+
+    #[tokio::test]
+    async fn test_nest_and_merge() {
+        async fn mark(c: &mut Context) {
+            c.next().await;
+            let marks = c
+                .response
+                .headers()
+                .get("x-mark")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            c.set_header("x-mark", &format!("{}{}", marks, "m"));
+        }
+        async fn show(c: &mut Context) -> String {
+            format!(
+                "{} {}",
+                c.param::<String>("id").unwrap(),
+                c.url_for("user.show", &[("id", "7")]).unwrap()
+            )
+        }
+
+        //子Engine：自己的全局中间件、分组、命名路由与404
+        let mut users = new()
+            .hooks(mark)
+            .get("/users/:id", show)
+            .name("user.show")
+            .no_route(async |_c: &mut Context| (StatusCode::NOT_FOUND, "api 404"));
+        users
+            .group("/admin")
+            .hooks(mark)
+            .get("/stats", async |_c: &mut Context| "stats");
+        let health = new().get("/health", async |_c: &mut Context| "ok");
+        let engin = Arc::new(
+            new()
+                .get("/", async |_c: &mut Context| "home")
+                .nest("/api/", users)
+                .merge(health),
+        );
+
+        let resp = call(&engin, Method::GET, "/api/users/1").await;
+        assert_eq!(resp.headers()["x-mark"], "m");
+        assert_eq!(body_string(resp).await, "1 /api/users/7");
+        //子Engine的全局中间件 + 分组中间件
+        let resp = call(&engin, Method::GET, "/api/admin/stats").await;
+        assert_eq!(resp.headers()["x-mark"], "mm");
+        assert_eq!(body_string(resp).await, "stats");
+        //子Engine的全局中间件不影响其他路由
+        let resp = call(&engin, Method::GET, "/").await;
+        assert!(!resp.headers().contains_key("x-mark"));
+        let resp = call(&engin, Method::GET, "/health").await;
+        assert_eq!(body_string(resp).await, "ok");
+        //前缀下的404
+        let resp = call(&engin, Method::GET, "/api/none").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()["x-mark"], "m");
+        assert_eq!(body_string(resp).await, "api 404");
+        let resp = call(&engin, Method::GET, "/none").await;
+        assert_eq!(body_string(resp).await, "404 not found");
+
+        let routes: Vec<_> = engin
+            .routes()
+            .into_iter()
+            .map(|r| (r.pattern, r.group, r.middlewares))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("/".to_string(), None, 0),
+                (
+                    "/api/admin/stats".to_string(),
+                    Some("/api/admin".to_string()),
+                    2
+                ),
+                ("/api/users/:id".to_string(), Some("/api".to_string()), 1),
+                ("/health".to_string(), None, 0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mount_isolates_middlewares() {
+        //在x-tag头中记录经过的中间件
+        fn tag(name: &'static str) -> impl for<'a> HandlerFn<'a> {
+            from_fn(move |c| {
+                Box::pin(async move {
+                    c.next().await;
+                    let tags = c
+                        .response
+                        .headers()
+                        .get("x-tag")
+                        .map(|v| v.to_str().unwrap().to_string())
+                        .unwrap_or_default();
+                    c.set_header("x-tag", &format!("{}{}", name, tags));
+                })
+            })
+        }
+        async fn deny(c: &mut Context) {
+            c.abort_with_status(StatusCode::UNAUTHORIZED);
+        }
+        async fn ok(_c: &mut Context) -> &'static str {
+            "ok"
+        }
+        let tags = |resp: &Response<Body>| {
+            resp.headers()
+                .get("x-tag")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        };
+
+        //与子Engine前缀相同的分组：各自只经过自己的中间件
+        let mut engin = new().hooks(tag("g"));
+        engin.group("/api").hooks(tag("p")).get("/status", ok);
+        let sub = new().hooks(tag("s")).get("/users", ok);
+        let engin = Arc::new(engin.nest("/api", sub));
+        let resp = call(&engin, Method::GET, "/api/status").await;
+        assert_eq!(tags(&resp), "gp");
+        let resp = call(&engin, Method::GET, "/api/users").await;
+        assert_eq!(tags(&resp), "gs");
+
+        //挂载到同一前缀的两个子Engine
+        let a = new().hooks(deny).get("/a", ok);
+        let b = new().get("/b", ok);
+        let engin = Arc::new(new().nest("/x", a).nest("/x", b));
+        let resp = call(&engin, Method::GET, "/x/a").await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = call(&engin, Method::GET, "/x/b").await;
+        assert_eq!(body_string(resp).await, "ok");
+
+        //merge的分组中间件不影响当前Engine前缀相同的路由
+        let mut other = new();
+        other.group("/admin").hooks(deny).get("/x", ok);
+        let mut engin = new();
+        engin.group("/admin").get("/y", ok);
+        let engin = Arc::new(engin.merge(other));
+        let resp = call(&engin, Method::GET, "/admin/x").await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = call(&engin, Method::GET, "/admin/y").await;
+        assert_eq!(body_string(resp).await, "ok");
+
+        //多层挂载：中间层的全局中间件作用于其下所有路由
+        let inner = new().hooks(tag("i")).get("/z", ok);
+        let outer = new().hooks(tag("o")).get("/y", ok).nest("/in", inner);
+        let engin = Arc::new(new().nest("/out", outer));
+        let resp = call(&engin, Method::GET, "/out/in/z").await;
+        assert_eq!(tags(&resp), "oi");
+        let resp = call(&engin, Method::GET, "/out/y").await;
+        assert_eq!(tags(&resp), "o");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "route `GET /api/users` conflicts with existing route `GET /api/users`"
+    )]
+    fn test_nest_conflict() {
+        let users = new().get("/users", async |_c: &mut Context| {});
+        let _ = new()
+            .get("/api/users", async |_c: &mut Context| {})
+            .nest("/api", users);
+    }
+
+    #[test]
+    #[should_panic(expected = "route name `home` for `/b` is already used by `/a`")]
+    fn test_merge_name_conflict() {
+        let other = new().get("/b", async |_c: &mut Context| {}).name("home");
+        let _ = new()
+            .get("/a", async |_c: &mut Context| {})
+            .name("home")
+            .merge(other);
+    }
+
+    #[tokio::test]
+    async fn test_host() {
+        async fn call_host(engin: &Arc<Engine>, host: &str, uri: &str) -> Response<Body> {
+            let req = Request::builder()
+                .uri(uri)
+                .header(header::HOST, host)
+                .body(Body::empty())
+                .unwrap();
+            Engine::handler(req, engin.clone()).await.unwrap()
+        }
+
+        let mut engin = new()
+            .get("/", async |_c: &mut Context| "home")
+            .get("/health", async |_c: &mut Context| "ok");
+        engin
+            .host(":tenant.example.com")
+            .get("/", async |c: &mut Context| {
+                c.host_param::<String>("tenant").unwrap()
+            })
+            .get("/team", async |c: &mut Context| {
+                c.param::<String>("tenant").unwrap()
+            })
+            .get("/user/:tenant", async |c: &mut Context| {
+                format!(
+                    "{}|{}",
+                    c.host_param::<String>("tenant").unwrap(),
+                    c.param::<String>("tenant").unwrap()
+                )
+            })
+            .no_route(async |_c: &mut Context| (StatusCode::NOT_FOUND, "tenant 404"));
+        engin
+            .host("Admin.example.com")
+            .get("/", async |_c: &mut Context| "admin")
+            .group("/users")
+            .get("/:id", async |c: &mut Context| {
+                c.param::<String>("id").unwrap()
+            });
+        let engin = Arc::new(engin);
+
+        //精确host优先于带参数的host，忽略端口与大小写
+        let resp = call_host(&engin, "ADMIN.example.com:8080", "/").await;
+        assert_eq!(body_string(resp).await, "admin");
+        let resp = call_host(&engin, "admin.example.com", "/users/7").await;
+        assert_eq!(body_string(resp).await, "7");
+        //host中的参数
+        let resp = call_host(&engin, "acme.example.com", "/").await;
+        assert_eq!(body_string(resp).await, "acme");
+        //路径中没有同名参数时，param读取host参数
+        let resp = call_host(&engin, "acme.example.com", "/team").await;
+        assert_eq!(body_string(resp).await, "acme");
+        //host参数与同名的路径参数互不覆盖，param优先读取路径参数
+        let resp = call_host(&engin, "acme.example.com", "/user/bob").await;
+        assert_eq!(body_string(resp).await, "acme|bob");
+        //未匹配的host或路径使用不区分host的路由
+        let resp = call_host(&engin, "example.com", "/").await;
+        assert_eq!(body_string(resp).await, "home");
+        let resp = call_host(&engin, "acme.example.com", "/health").await;
+        assert_eq!(body_string(resp).await, "ok");
+        //host分组的404只作用于该host
+        let resp = call_host(&engin, "acme.example.com", "/none").await;
+        assert_eq!(body_string(resp).await, "tenant 404");
+        let resp = call_host(&engin, "admin.example.com", "/none").await;
+        assert_eq!(body_string(resp).await, "404 not found");
+
+        let routes: Vec<_> = engin
+            .routes()
+            .into_iter()
+            .map(|r| (r.host, r.pattern, r.group))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                (None, "/".to_string(), None),
+                (None, "/health".to_string(), None),
+                (
+                    Some(":tenant.example.com".to_string()),
+                    "/".to_string(),
+                    None
+                ),
+                (
+                    Some(":tenant.example.com".to_string()),
+                    "/team".to_string(),
+                    None
+                ),
+                (
+                    Some(":tenant.example.com".to_string()),
+                    "/user/:tenant".to_string(),
+                    None
+                ),
+                (Some("admin.example.com".to_string()), "/".to_string(), None),
+                (
+                    Some("admin.example.com".to_string()),
+                    "/users/:id".to_string(),
+                    Some("/users".to_string())
+                ),
+            ]
+        );
     }
 }
@@ -1,6 +1,6 @@
 mod engin;
-pub use engin::{Engine,new,default};
+pub use engin::{Engine,PathPolicy,new,default};
 
+#[allow(clippy::module_inception)]
 mod server;
 pub use server::*;
-
@@ -13,6 +13,7 @@ pub struct Server {}
 impl Server {
     pub async fn run(addr: SocketAddr, engin: Engine) {
         let engin = Arc::new(engin);
+        info!("路由表：\n{}", engin.route_table());
         debug!("路由注册表：{:#?}", &engin.router);
         let make_service = make_service_fn(|conn: &AddrStream| {
             let _addr = conn.remote_addr();
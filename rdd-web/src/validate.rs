@@ -0,0 +1,203 @@
+use std::fmt::Display;
+
+pub use regex::Regex;
+
+//请求数据校验：绑定json/query/form后执行，如：
+//impl Validate for User {
+//    fn validate(&self) -> Result<(), ValidationErrors> {
+//        let mut v = ValidationErrors::new();
+//        v.length("name", &self.name, 1, 32);
+//        v.range("age", self.age, 1, 150);
+//        v.email("email", &self.email);
+//        v.into_result()
+//    }
+//}
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+//单个字段的校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+//一次校验收集到的全部字段错误，转换为tiny::Error后返回422
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<F, M>(&mut self, field: F, message: M)
+    where
+        F: Into<String>,
+        M: Into<String>,
+    {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    //合并嵌套结构体的校验结果，字段名加上前缀：address.city
+    pub fn nested<V>(&mut self, field: &str, value: &V)
+    where
+        V: Validate,
+    {
+        if let Err(e) = value.validate() {
+            for error in e.errors {
+                self.add(format!("{}.{}", field, error.field), error.message);
+            }
+        }
+    }
+
+    //条件不满足时记录错误
+    pub fn check(&mut self, field: &str, ok: bool, message: &str) {
+        if !ok {
+            self.add(field, message);
+        }
+    }
+
+    //数值范围（包含边界）
+    pub fn range<T>(&mut self, field: &str, value: T, min: T, max: T)
+    where
+        T: PartialOrd + Display,
+    {
+        if value < min || value > max {
+            self.add(field, format!("must be between {} and {}", min, max));
+        }
+    }
+
+    //字符长度（按字符计算，包含边界）
+    pub fn length(&mut self, field: &str, value: &str, min: usize, max: usize) {
+        let len = value.chars().count();
+        if len < min || len > max {
+            self.add(field, format!("length must be between {} and {}", min, max));
+        }
+    }
+
+    pub fn pattern(&mut self, field: &str, value: &str, re: &Regex) {
+        if !re.is_match(value) {
+            self.add(field, format!("must match pattern {}", re.as_str()));
+        }
+    }
+
+    pub fn email(&mut self, field: &str, value: &str) {
+        if !is_email(value) {
+            self.add(field, "must be a valid email address");
+        }
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "validation failed(")?;
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+//简单的邮箱格式检查：local@domain.tld
+fn is_email(value: &str) -> bool {
+    let (local, domain) = match value.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.contains(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Address {
+        city: String,
+    }
+
+    impl Validate for Address {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut v = ValidationErrors::new();
+            v.length("city", &self.city, 1, 16);
+            v.into_result()
+        }
+    }
+
+    struct User {
+        name: String,
+        age: u8,
+        email: String,
+        code: String,
+        address: Address,
+    }
+
+    impl Validate for User {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut v = ValidationErrors::new();
+            v.length("name", &self.name, 1, 4);
+            v.range("age", self.age, 1, 150);
+            v.email("email", &self.email);
+            v.pattern("code", &self.code, &Regex::new(r"^[a-z]+$").unwrap());
+            v.nested("address", &self.address);
+            v.into_result()
+        }
+    }
+
+    #[test]
+    fn test_validate_collects_all_errors() {
+        let user = User {
+            name: "张三丰啊".to_string(),
+            age: 0,
+            email: "a@b".to_string(),
+            code: "A1".to_string(),
+            address: Address {
+                city: "".to_string(),
+            },
+        };
+        let errors = user.validate().unwrap_err();
+        let fields: Vec<_> = errors.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["age", "email", "code", "address.city"]);
+    }
+
+    #[test]
+    fn test_email() {
+        assert!(is_email("hmm@example.com"));
+        assert!(!is_email("hmm@example"));
+        assert!(!is_email("@example.com"));
+        assert!(!is_email("hmm@@example.com"));
+        assert!(!is_email("h m@example.com"));
+        assert!(!is_email("hmm@example..com"));
+    }
+}